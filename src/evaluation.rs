@@ -2,11 +2,13 @@ use self::piece_squares::score_piece_squares;
 use self::piece_values::evaluate_pieces;
 
 use crate::constants::{BLACK, WHITE};
+use crate::evaluation::eval_config::{weighted, EvalConfig};
 use crate::evaluation::mobility::score_mobility;
 use crate::evaluation::pawn_structure::{PawnEvalHashTable};
 use crate::moving::move_generation::MoveGenerator;
 use crate::position::board::Board;
 mod board_state;
+pub mod eval_config;
 pub mod piece_values;
 mod pawn_structure;
 mod mobility;
@@ -18,20 +20,26 @@ mod greed;
 
 pub struct Evaluator {
     pawn_hash: PawnEvalHashTable,
+    config: EvalConfig,
 }
 
 impl Evaluator {
     pub fn new() -> Self {
-        Evaluator { pawn_hash: PawnEvalHashTable::new() }
+        Evaluator { pawn_hash: PawnEvalHashTable::new(), config: EvalConfig::default() }
+    }
+
+    pub fn set_config(&mut self, config: EvalConfig) {
+        self.config = config;
     }
 
     pub fn evaluate(&mut self, board: &Board, mg: &MoveGenerator) -> i32 {
         let pre_eval_result = self.run_pre_eval(board);
         let pieces = evaluate_pieces(board, &pre_eval_result);
         let piece_squares = score_piece_squares(board, &pre_eval_result);
-        let pawn_score = self.score_pawns(board, &pre_eval_result);
-        let king_safety = self.evaluate_king_safety(board, mg);
+        let pawn_score = weighted(self.score_pawns(board, &pre_eval_result), self.config.pawn_structure);
+        let king_safety = weighted(self.evaluate_king_safety(board, mg), self.config.king_safety);
         let mobility_score = score_mobility(board, mg, WHITE, BLACK, pre_eval_result.phase) - score_mobility(board, mg, BLACK, WHITE, pre_eval_result.phase);
+        let mobility_score = weighted(mobility_score, self.config.mobility);
         let greed_score = self.score_queen_greed(board, pre_eval_result.phase);
         let score = pieces + piece_squares + pawn_score + king_safety + mobility_score + greed_score;
         if board.us == WHITE {
@@ -0,0 +1,17 @@
+// weights of the evaluation terms in percent, 100 leaves a term as tuned
+#[derive(Clone, Copy)]
+pub struct EvalConfig {
+    pub mobility: i32,
+    pub king_safety: i32,
+    pub pawn_structure: i32,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        EvalConfig { mobility: 100, king_safety: 100, pawn_structure: 100 }
+    }
+}
+
+pub fn weighted(score: i32, weight: i32) -> i32 {
+    score * weight / 100
+}
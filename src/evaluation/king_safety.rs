@@ -139,7 +139,7 @@ fn interp_by_my_material(score: i32, pieces: &PieceSet) -> i32 {
     let queens = pieces.get_queens().count_ones();
     let material_val = knights * KNIGHT_PHASE_VALUE + bishops * BISHOP_PHASE_VALUE + rooks * ROOK_PHASE_VALUE + queens * QUEEN_PHASE_VALUE;
     let material_val = material_val as i32;
-    return material_val * score / MAX_MATERIAL_VALUE as i32;
+    material_val * score / MAX_MATERIAL_VALUE as i32
 }
 
 
@@ -57,6 +57,7 @@ fn open_file_rook(pieces: &PieceSet, enemy_pawns: u64) -> i32 {
 }
 
 #[inline]
+#[allow(clippy::too_many_arguments)]
 fn compute_mobility(mg: &MoveGenerator, mut piece_mask: u64, piece: usize, occ: u64, us: u64, color: usize, table: &[i32], pawn_attacks: u64) -> i32 {
     let mut score = 0;
     
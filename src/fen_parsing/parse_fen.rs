@@ -1,3 +1,4 @@
+use crate::moving::move_generation::{attackers_to_exist, get_mg};
 use crate::position::board::Board;
 use crate::position::zobrist_hashing::ZobristHasher;
 use crate::constants::*;
@@ -32,13 +33,31 @@ pub fn parse_fen(fen: &str) -> Result<Board, FenError> {
         board = parse_move_clock(board, splits[5])?;
     }
     board.compute_occ_and_checkers();
+    check_legality(&board)?;
     board.compute_hash();
     Ok(board)
 }
 
+// positions that can not come up in a game, searching them would capture a king
+fn check_legality(board: &Board) -> Result<(), FenError> {
+    let illegal = |reason: &str| Err(FenError::InvalidStructure { reason: reason.to_owned() });
+    let pawns = board.players[WHITE].get_pawns() | board.players[BLACK].get_pawns();
+    if pawns & (RANK1 | RANK8) != 0 {
+        return illegal("Pawns can not stand on the first or last rank");
+    }
+    let white_king = board.players[WHITE].get_king();
+    if get_mg().get_king_attacks(white_king.trailing_zeros() as usize) & board.players[BLACK].get_king() != 0 {
+        return illegal("The kings can not stand next to each other");
+    }
+    if attackers_to_exist(board, board.players[board.enemy].get_king(), board.get_occupancy(), board.us) != 0 {
+        return illegal("The side not to move can not be in check");
+    }
+    Ok(())
+}
+
 fn parse_pieces(mut board: Board, piece_str: &str) -> Result<Board, FenError> {
     let ranks: Vec<&str> = piece_str.split("/").collect();
-    if ranks.len() < 8 {
+    if ranks.len() != 8 {
         return Err(FenError::InvalidRankCount { found: ranks.len() });
     }
     let mut pos = 0;
@@ -73,6 +92,12 @@ fn parse_pieces(mut board: Board, piece_str: &str) -> Result<Board, FenError> {
             });
         }
     }
+    // move generation relies on both kings being on the board
+    if board.players.iter().any(|pieces| pieces.get_king().count_ones() != 1) {
+        return Err(FenError::InvalidStructure {
+            reason: "Each side needs exactly one king".to_owned(),
+        });
+    }
     Ok(board)
 }
 
@@ -367,4 +392,22 @@ mod test {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1";
         assert!(parse_fen(fen).is_err());
     }
+
+    #[test]
+    fn should_err_instead_of_panicking_on_impossible_boards() {
+        assert!(parse_fen("rnbqkbnr/pppppppp/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+        assert!(parse_fen("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+    }
+
+    #[test]
+    fn should_err_on_positions_that_can_not_arise_in_a_game() {
+        // kings next to each other, the side not to move in check, pawns on the back ranks
+        assert!(parse_fen("8/8/8/3kK3/8/8/8/8 w - - 0 1").is_err());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K2R b - - 0 1").is_ok());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").is_ok());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").is_ok());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").is_err());
+        assert!(parse_fen("P3k3/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+        assert!(parse_fen("4k3/8/8/8/8/8/8/3pK3 b - - 0 1").is_err());
+    }
 }
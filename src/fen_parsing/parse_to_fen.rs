@@ -168,7 +168,7 @@ mod test {
 
     #[test]
     fn should_parse_back_in_endgame() {
-        let fen = "8/8/8/8/8/8/4k3/4K2R w - - 0 1";
+        let fen = "8/8/8/8/8/4k3/8/4K2R w - - 0 1";
         should_parse_fen_and_back(fen);
     }
 
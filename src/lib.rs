@@ -1,4 +1,7 @@
+use std::io::{stdin, stdout, BufRead, BufReader, Cursor, Read, Write};
+
 use uci::uci_interpreter::UciController;
+use xboard::xboard_interpreter::XBoardController;
 
 pub mod bitboard_helpers;
 pub mod position;
@@ -8,10 +11,26 @@ pub mod fen_parsing;
 pub mod moving;
 pub mod search;
 pub mod uci;
+pub mod xboard;
 pub mod tests;
 
 
 pub fn run() {
-    let mut uci_controller = UciController::new();
-    uci_controller.run();
+    run_with(BufReader::new(stdin()), stdout());
+}
+
+// the first command picks the protocol, xboard guis open with 'xboard' and anything else is uci
+pub fn run_with<R: BufRead + Send + 'static, W: Write + Send + 'static>(mut input: R, output: W) {
+    let mut first = String::new();
+    while first.trim().is_empty() {
+        match input.read_line(&mut first) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+    }
+    if first.trim() == "xboard" {
+        XBoardController::new(output).run(input);
+    } else {
+        UciController::new(output).run(Cursor::new(first).chain(input));
+    }
 }
@@ -99,7 +99,8 @@ impl MoveGenerator {
         let cap_bb = 1 << cap_sq;
         occ &= !(start_bb | cap_bb);
         occ |= target_bb;
-        let attackers = self.attackers_to_exist(board, king, occ, board.enemy);
+        // the captured pawn may be the one giving check
+        let attackers = self.attackers_to_exist(board, king, occ, board.enemy) & !cap_bb;
         attackers == 0
     }
 
@@ -162,3 +163,17 @@ impl MoveGenerator {
     }
 
 }
+
+#[cfg(test)]
+mod test {
+    use crate::fen_parsing::parse_fen::parse_fen;
+    use crate::moving::move_generation::{generate_moves, is_legal};
+
+    #[test]
+    fn should_allow_en_passant_capture_of_checking_pawn() {
+        let board = parse_fen("6k1/8/4p2p/4P1pP/p3P2K/6r1/5b2/8 w - g6 0 53").unwrap();
+        let moves = generate_moves(&board);
+        let ep = moves.iter().find(|mv| mv.is_en_passant()).unwrap();
+        assert!(is_legal(ep, &board));
+    }
+}
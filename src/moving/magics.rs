@@ -72,7 +72,7 @@ fn get_bishop_attacks_from_occ(occ: u64, sq: usize) -> u64 {
     // NW
     let mut t_sq = sq;
     let mut ptr = 1u64 << sq;
-    while t_sq % 8 != 0 && t_sq / 8 != 7 && ptr != 0 {
+    while !t_sq.is_multiple_of(8) && t_sq / 8 != 7 && ptr != 0 {
         t_sq += 7;
         ptr <<= 7;
         result |= ptr;
@@ -92,7 +92,7 @@ fn get_bishop_attacks_from_occ(occ: u64, sq: usize) -> u64 {
     //SW 
     t_sq = sq;
     ptr = 1u64 << sq;
-    while t_sq % 8 != 0 && t_sq / 8 != 0 && ptr != 0 {
+    while !t_sq.is_multiple_of(8) && t_sq / 8 != 0 && ptr != 0 {
         t_sq -= 9;
         ptr >>= 9;
         result |= ptr;
@@ -117,7 +117,7 @@ fn get_rook_attacks_from_occ(occ: u64, sq: usize) -> u64 {
     let mut t_sq = sq;
     let mut ptr = 1u64 << sq;
     // left
-    while t_sq % 8 != 0 && ptr != 0 {
+    while !t_sq.is_multiple_of(8) && ptr != 0 {
         t_sq -= 1;
         ptr >>= 1;
         result |= ptr;
@@ -208,7 +208,7 @@ const fn get_diagonals(sq: usize) -> u64 {
     let mut result = 0u64;
     // NW
     let mut tsq = sq;
-    while tsq % 8 != 0 && tsq / 8 != 7 {
+    while !tsq.is_multiple_of(8) && tsq / 8 != 7 {
         tsq += 7;
         result |= 1 << tsq;
     }
@@ -220,7 +220,7 @@ const fn get_diagonals(sq: usize) -> u64 {
     }
     // SW
     tsq = sq;
-    while tsq % 8 != 0 && tsq / 8 != 0 {
+    while !tsq.is_multiple_of(8) && tsq / 8 != 0 {
         tsq -= 9;
         result |= 1 << tsq;
     }
@@ -9,7 +9,7 @@ use std::sync::OnceLock;
 static MG: OnceLock<MoveGenerator> = OnceLock::new();
 
 pub fn get_mg() -> &'static MoveGenerator {
-    MG.get_or_init(|| MoveGenerator::new())
+    MG.get_or_init(MoveGenerator::new)
 }
 
 pub struct MoveGenerator {
@@ -499,9 +499,9 @@ const fn compute_between_bb_lookup() -> [[u64; 64]; 64] {
                 1
             } else if i % 8 == j % 8 {
                 8
-            } else if i.abs_diff(j) % 9 == 0 {
+            } else if i.abs_diff(j).is_multiple_of(9) {
                 9
-            } else if i.abs_diff(j) % 7 == 0 {
+            } else if i.abs_diff(j).is_multiple_of(7) {
                 7
             } else {
                 j += 1;
@@ -54,6 +54,10 @@ impl MoveList {
     pub fn get_move(&self, i: usize) -> &Move {
         &self.moves[i]
     }
+
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves[..self.count]
+    }
 }
 
 pub struct Iter<'a> {
@@ -94,7 +98,7 @@ impl <'a> ExactSizeIterator for Iter<'a> {
 }
 
 impl MoveList {
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter {
             moves: &self.moves[..self.count],
             index: 0,
@@ -1,16 +1,10 @@
 use crate::constants::*;
 
-#[derive(Default, PartialEq, Copy)]
+#[derive(Default, PartialEq, Clone, Copy)]
 pub struct Move {
     value: u16,
 }
 
-impl Clone for Move {
-    fn clone(&self) -> Self {
-        Move { value: self.value }
-    }
-}
-
 impl Move {
     pub fn new(start: u16, target: u16, code: u16) -> Move {
         Move {
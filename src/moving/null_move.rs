@@ -10,6 +10,8 @@ impl Board {
         }
         let mut new_state = state.clone();
         new_state.clear_en_passant_file();
+        // positions before the null move can't repeat through it
+        new_state.clear_halfmove_clock();
         self.hasher.toggle_moving_side();
         self.push_state_stack(new_state);
         (self.us, self.enemy) = (self.enemy, self.us);
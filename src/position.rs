@@ -1,4 +1,5 @@
 pub mod board;
 pub mod board_state;
+pub mod draws;
 pub mod piece_set;
 pub mod zobrist_hashing;
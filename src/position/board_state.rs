@@ -78,8 +78,11 @@ impl BoardState {
         self.value = (self.value & !HALFMOVE_CLOCK_MASK) | (halfmove_clock << 9);
     }
 
+    // saturates instead of overflowing into the move clock
     pub fn increment_halfmove_clock(&mut self) {
-        self.value += 1 << 9;
+        if self.get_halfmove_clock() < 0xFF {
+            self.value += 1 << 9;
+        }
     }
 
     pub fn clear_halfmove_clock(&mut self) {
@@ -0,0 +1,97 @@
+use crate::constants::{BLACK, WHITE};
+use crate::moving::move_generation::generate_moves;
+
+use super::board::Board;
+
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+const FIFTY_MOVE_PLIES: u32 = 100;
+
+impl Board {
+    // plies_from_root separates the search line from the game history before it,
+    // a repetition inside the search is enough, one in the game needs the third occurrence
+    pub fn is_draw(&self, plies_from_root: i32) -> bool {
+        self.is_repetition(plies_from_root) || self.is_insufficient_material() || self.is_fifty_move_draw()
+    }
+
+    // mate on the hundredth ply still counts
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.get_state().get_halfmove_clock() >= FIFTY_MOVE_PLIES
+            && (!self.is_check() || generate_moves(self).get_count() > 0)
+    }
+
+    // only positions since the last capture, pawn move or null move can repeat
+    pub fn is_repetition(&self, plies_from_root: i32) -> bool {
+        let hash = self.get_hash();
+        let hash_stack = self.get_hash_stack();
+        let reversible = (self.get_state().get_halfmove_clock() as usize).min(hash_stack.len());
+        let mut game_repetitions = 0;
+        for plies_back in (4..=reversible).step_by(2) {
+            if hash_stack[hash_stack.len() - plies_back] != hash {
+                continue;
+            }
+            if plies_back as i32 <= plies_from_root {
+                return true;
+            }
+            game_repetitions += 1;
+            if game_repetitions == 2 {
+                return true;
+            }
+        }
+        false
+    }
+
+    // no side can mate with a lone minor piece or bishops all on one colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let (white, black) = (self.get_pieces(WHITE), self.get_pieces(BLACK));
+        if white.get_pawns() | black.get_pawns() | white.get_orthogonals() | black.get_orthogonals() != 0 {
+            return false;
+        }
+        let knights = white.get_knights() | black.get_knights();
+        let bishops = white.get_diagonals() | black.get_diagonals();
+        (knights | bishops).count_ones() <= 1
+            || (knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fen_parsing::parse_fen::parse_fen;
+    use crate::moving::move_generation::generate_moves;
+    use crate::position::board::Board;
+
+    #[test]
+    fn should_detect_insufficient_material() {
+        for fen in ["8/8/4k3/8/8/3K4/8/8 w - - 0 1", "8/8/4k3/8/8/3KN3/8/8 w - - 0 1", "8/8/4k3/8/8/3KB3/8/8 w - - 0 1", "8/8/4kb2/8/8/3KB3/8/8 w - - 0 1"] {
+            assert!(parse_fen(fen).unwrap().is_insufficient_material(), "{fen}");
+        }
+        for fen in ["8/8/4k3/8/8/3K4/4P3/8 w - - 0 1", "8/8/4kb2/8/8/3K1B2/8/8 w - - 0 1", "8/8/4kn2/8/8/3KB3/8/8 w - - 0 1"] {
+            assert!(!parse_fen(fen).unwrap().is_insufficient_material(), "{fen}");
+        }
+    }
+
+    fn play(board: &mut Board, moves: &[&str]) {
+        for mv_s in moves {
+            let legal = generate_moves(board);
+            let mv = *legal.iter().find(|m| m.to_str() == *mv_s).unwrap();
+            board.make_move(&mv);
+        }
+    }
+
+    #[test]
+    fn should_count_game_repetitions_as_threefold() {
+        let mut board = parse_fen("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap();
+        let shuffle = ["d3c3", "e6f6", "c3d3", "f6e6"];
+        play(&mut board, &shuffle);
+        // a twofold is enough inside the search line, not in the game history
+        assert!(board.is_repetition(4));
+        assert!(!board.is_repetition(0));
+        play(&mut board, &shuffle);
+        assert!(board.is_repetition(0));
+    }
+
+    #[test]
+    fn should_score_fifty_moves_as_draw() {
+        assert!(parse_fen("8/8/4k3/8/8/3K4/R7/8 w - - 100 80").unwrap().is_fifty_move_draw());
+        assert!(!parse_fen("8/8/4k3/8/8/3K4/R7/8 w - - 99 80").unwrap().is_fifty_move_draw());
+    }
+}
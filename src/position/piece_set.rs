@@ -198,11 +198,11 @@ impl PieceSet {
         self.color
     }
 
-    pub fn iter(&self) -> PieceSetIter {
+    pub fn iter(&self) -> PieceSetIter<'_> {
         PieceSetIter::new(&self.pieces)
     }
 
-    pub fn iter_w_vals(&self) -> PieceSetValuesIter {
+    pub fn iter_w_vals(&self) -> PieceSetValuesIter<'_> {
         PieceSetValuesIter::new(&self.pieces)
     }
 
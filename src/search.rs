@@ -1,5 +1,12 @@
 pub mod alpha_beta;
-mod transposition;
+pub mod transposition;
+pub mod clock;
+pub mod extensions;
+pub mod pruning;
+pub mod see;
+pub mod skill;
+pub mod observer;
 mod move_ordering;
 mod history;
 mod killers;
+mod pv_table;
@@ -1,29 +1,51 @@
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::{f64, thread};
-use std::sync::atomic::AtomicBool;
-use std::time::{Duration, Instant};
+use std::f64;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::time::Instant;
 
+use crate::constants::WHITE;
 use crate::evaluation::Evaluator;
+use crate::evaluation::eval_config::EvalConfig;
 use crate::moving::move_generation::{generate_moves, get_mg};
 use crate::position::piece_set::PieceSet;
-use crate::search::history::HistoryTable;
+use crate::search::clock::SearchClock;
+use crate::search::extensions::ExtensionConfig;
+use crate::search::history::{moving_piece, HistoryTable, MoveContext, PieceTo};
+use crate::search::pruning::PruningConfig;
+use crate::search::see::{captured_value, see};
 use crate::search::killers::KillerTable;
 use crate::search::move_ordering::{OrderedMovesIter, QuiesceOrderedMovesIter};
+use crate::search::observer::{Bound, Score, SearchInfo, SearchObserver, SilentObserver};
+use crate::search::pv_table::PvTable;
 use crate::search::transposition::TTEntryType;
 use crate::{
     position::board::Board, 
-    moving::mv::Move
+    moving::{move_list::MoveList, mv::Move}
 };
 
-use super::{
-    transposition::{Entry, TTable},
-};
+use super::transposition::{Entry, TTable};
 
 
 const INFINITY: i32 = 10_000_000;
-const MATE: i32 = 1_000_000;
+pub const MATE: i32 = 1_000_000;
+// anything above this is a forced mate, the difference to MATE is the distance in plies
+pub const MATE_BOUND: i32 = MATE - 1000;
 const NULL_MOVE_RED: i32 = 3;
+const MAX_HELPER_DEPTH: i32 = 64;
+const ASPIRATION_MIN_DEPTH: i32 = 4;
+const ASPIRATION_WINDOW: i32 = 25;
+pub const MAX_MULTI_PV: usize = 218;
+pub const MAX_PLY: usize = 128;
+const LMR_HISTORY_DIVISOR: i32 = 8192;
+const DELTA_MARGIN: i32 = 200;
+const SINGULAR_MIN_DEPTH: i32 = 6;
+const SINGULAR_TT_DEPTH_MARGIN: i32 = 3;
+const SINGULAR_MARGIN_PER_DEPTH: i32 = 2;
+// the clock is read once every this many nodes
+const TIME_CHECK_NODES: u64 = 1024;
+// nodes are added to the count shared by all threads in batches of this many
+const NODE_BATCH: u64 = 1024;
 
 
 pub struct SearchResult {
@@ -35,46 +57,199 @@ pub struct SearchResult {
 }
 
 
+#[derive(Clone, Copy, Default)]
+struct StackEntry {
+    mv: Move,
+    extensions: i32,
+    excluded: Move,
+    static_eval: i32,
+    piece_to: PieceTo,
+}
+
 pub struct Searcher {
-    ttable: TTable,
+    ttable: Arc<TTable>,
     history: HistoryTable,
     killers: KillerTable,
     evaluator: Evaluator,
     search_depth: i32,
     ttable_hits: i32,
     nodes_searched: u64,
-    generation: i32,
+    // nodes of every thread of the search, reset by the main searcher
+    shared_nodes: Arc<AtomicU64>,
     nmp_hits: i32,
+    prev_score: Option<i32>,
+    multi_pv: usize,
+    root_lines: Vec<(i32, Move)>,
+    // principal variation of every root line, same order
+    root_pvs: Vec<Vec<Move>>,
+    pv_table: PvTable,
+    root_moves: Vec<Move>,
+    // the root moves after the first are rotated this far, so helpers fan out over the root
+    root_rotation: usize,
+    extensions: ExtensionConfig,
+    pruning: PruningConfig,
+    // no pruning or reductions, so a mate within the depth can't be cut away
+    full_width: bool,
+    qsearch_checks: bool,
+    contempt: i32,
+    root_side: usize,
+    node_limit: u64,
+    time_limit: Option<u128>,
+    clock: SearchClock,
+    seldepth: i32,
+    start: Instant,
+    observer: Arc<dyn SearchObserver>,
+    // per ply: the move that led to the node and the extensions spent on the line so far
+    stack: [StackEntry; MAX_PLY],
     stop: Arc<AtomicBool>,
+    is_main: bool,
     lmr_table: [[i32; 64]; 218]
 }
 
 impl Default for Searcher {
     fn default() -> Self {
+        Searcher::new_shared(Arc::new(TTable::default()), true)
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // helper threads share the table with the main searcher and stay silent
+    pub fn new_shared(ttable: Arc<TTable>, is_main: bool) -> Self {
         Searcher {
-            ttable: TTable::new(),
+            ttable,
             evaluator: Evaluator::new(),
             search_depth: 0,
             ttable_hits: 0,
             nodes_searched: 0,
-            generation: 0,
+            shared_nodes: Arc::new(AtomicU64::new(0)),
             nmp_hits: 0,
+            prev_score: None,
+            multi_pv: 1,
+            root_lines: Vec::new(),
+            root_pvs: Vec::new(),
+            pv_table: PvTable::new(),
+            root_moves: Vec::new(),
+            root_rotation: 0,
+            extensions: ExtensionConfig::default(),
+            pruning: PruningConfig::default(),
+            full_width: false,
+            // off by default, the extra nodes cost more than the mates they find at short time controls
+            qsearch_checks: false,
+            contempt: 0,
+            root_side: WHITE,
+            node_limit: u64::MAX,
+            time_limit: None,
+            clock: SearchClock::default(),
+            seldepth: 0,
+            start: Instant::now(),
+            observer: Arc::new(SilentObserver),
+            stack: [StackEntry::default(); MAX_PLY],
             stop: Arc::new(AtomicBool::new(false)),
+            is_main,
             history: HistoryTable::new(),
             killers: KillerTable::new(),
             lmr_table: compute_lmr_table()
         }
     }
-}
 
-impl Searcher {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.clamp(1, MAX_MULTI_PV);
+    }
+
+    // only the main searcher reports, helpers stay silent whatever they are given
+    pub fn set_observer(&mut self, observer: Arc<dyn SearchObserver>) {
+        self.observer = observer;
+    }
+
+    pub fn set_ttable(&mut self, ttable: Arc<TTable>) {
+        self.ttable = ttable;
+    }
+
+    // nothing learned in the previous game carries over
+    pub fn new_game(&mut self) {
+        self.history = HistoryTable::new();
+        self.killers = KillerTable::new();
+    }
+
+    pub fn set_extensions(&mut self, extensions: ExtensionConfig) {
+        self.extensions = extensions;
+    }
+
+    pub fn set_qsearch_checks(&mut self, qsearch_checks: bool) {
+        self.qsearch_checks = qsearch_checks;
+    }
+
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit.unwrap_or(u64::MAX);
+    }
+
+    // the search stops itself once the clock passes time_limit milliseconds
+    pub fn set_time_limit(&mut self, clock: SearchClock, time_limit: Option<u128>) {
+        self.clock = clock;
+        self.time_limit = time_limit;
+    }
+
+    // None while pondering
+    pub fn elapsed(&mut self) -> Option<u128> {
+        self.clock.elapsed()
+    }
+
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.contempt = contempt;
+    }
+
+    pub fn set_eval_config(&mut self, config: EvalConfig) {
+        self.evaluator.set_config(config);
+    }
+
+    pub fn set_pruning(&mut self, pruning: PruningConfig) {
+        self.pruning = pruning;
+    }
+
+    // switches off null move, lmr, futility, razoring and late move pruning, used by go mate
+    pub fn set_full_width(&mut self, full_width: bool) {
+        self.full_width = full_width;
+    }
+
+    // restricts the root to the given moves, an empty list means every legal move
+    pub fn set_root_moves(&mut self, root_moves: Vec<Move>) {
+        self.root_moves = root_moves;
+    }
+
+    pub fn set_root_rotation(&mut self, root_rotation: usize) {
+        self.root_rotation = root_rotation;
+    }
+
+    // helpers count their nodes towards the main searcher's, so info nodes covers every thread
+    pub fn shared_nodes(&self) -> Arc<AtomicU64> {
+        self.shared_nodes.clone()
+    }
+
+    pub fn set_shared_nodes(&mut self, shared_nodes: Arc<AtomicU64>) {
+        self.shared_nodes = shared_nodes;
+    }
+
+    // (score, move) for every line of the last completed iteration, best first
+    pub fn get_root_lines(&self) -> &[(i32, Move)] {
+        &self.root_lines
+    }
+
+    // principal variation of the best line of the last completed iteration
+    pub fn get_pv(&self) -> Vec<Move> {
+        self.root_pvs.first().cloned().unwrap_or_default()
     }
 
     //test exclusive
     pub fn search_to_depth(&mut self, board: &mut Board, depth: i32) -> SearchResult {
-        self.nodes_searched = 0;
+        self.reset_nodes();
+        self.prev_score = None;
+        self.root_lines.clear();
+        self.start = Instant::now();
+        self.killers = KillerTable::new();
         let mut best_mv = Move::null();
         for i in 1..(depth + 1) {
             (_, best_mv) = self.make_search(board, i);
@@ -84,7 +259,11 @@ impl Searcher {
 
     //test exclusive
     pub fn search_flat(&mut self, board: &mut Board, depth: i32) -> SearchResult {
-        self.nodes_searched =0;
+        self.reset_nodes();
+        self.prev_score = None;
+        self.root_lines.clear();
+        self.start = Instant::now();
+        self.killers = KillerTable::new();
         let (_, mv) = self.make_search(board, depth);
         SearchResult { depth_reached: self.search_depth, mv, nodes_searched: self.nodes_searched, ttable_hits: self.ttable_hits, nmp_hits: self.nmp_hits }
     }
@@ -92,163 +271,292 @@ impl Searcher {
 
     pub fn prepare_search(&mut self, stop: Arc<AtomicBool>) {
         self.stop = stop;
-        self.nodes_searched = 0;
-        self.stop.store(false, Ordering::Relaxed);
+        self.reset_nodes();
+        self.prev_score = None;
+        self.start = Instant::now();
+        self.root_lines.clear();
+        self.root_pvs.clear();
+        self.clock.restart();
+        self.killers = KillerTable::new();
+        self.history.age();
+        // entries of earlier searches age, those of this one count towards hashfull
+        if self.is_main {
+            self.ttable.new_search();
+        }
     }
 
     //test exclusive
     pub fn search_to_time(&mut self, board: &mut Board, time: u64, cut: bool) -> SearchResult {
-        self.nodes_searched = 0;
+        self.reset_nodes();
+        self.prev_score = None;
+        self.root_lines.clear();
+        self.start = Instant::now();
+        self.killers = KillerTable::new();
         self.stop.store(false, Ordering::Relaxed);
-        let time = time as u128;
-        let start = Instant::now();
-        let fallback_mv = *generate_moves(board).get_move(0);
-
-        thread::scope(|s| {
-            let stop = Arc::clone(&self.stop);
-
-            let handle = s.spawn(|| {
-                let mut depth = 1;
-                let mut best = Move::new_null_mv();
-                while (start.elapsed().as_millis() <= time / 2 || !cut)
-                && !self.stop.load(Ordering::Relaxed) {
-                    let (value, mv) = self.make_search(board, depth);
-                    if !self.stop.load(Ordering::Relaxed) {
-                        best = mv;
-                    }
-                    if value > MATE / 10 {
-                        break;
-                    }
-                    depth += 1;
-                }
-                if best.is_null() {
-                    let moves = generate_moves(board);
-                    best = *moves.get_move(0);
-                }
-                self.stop.store(true, Ordering::Relaxed);
-                SearchResult { depth_reached: self.search_depth, mv: best, nodes_searched: self.nodes_searched, ttable_hits: self.ttable_hits, nmp_hits: self.nmp_hits }
-            });
+        self.set_time_limit(SearchClock::default(), Some(time as u128));
 
-            while start.elapsed().as_millis() < time && !stop.load(Ordering::Relaxed) {
-                thread::sleep(Duration::from_millis(2));
+        let mut depth = 1;
+        let mut best = Move::new_null_mv();
+        while (!cut || self.elapsed().is_some_and(|t| t <= time as u128 / 2))
+        && !self.stop.load(Ordering::Relaxed) {
+            let (value, mv) = self.make_search(board, depth);
+            if !self.stop.load(Ordering::Relaxed) {
+                best = mv;
             }
-            stop.store(true, Ordering::Relaxed);
-            let result = handle.join();
-            match result {
-                Ok(sr) => sr,
-                Err(_) => SearchResult { mv: fallback_mv, nodes_searched: 0, depth_reached: 0, ttable_hits: 0 , nmp_hits: 0 }
+            if value > MATE / 10 {
+                break;
             }
-        })
+            depth += 1;
+        }
+        self.time_limit = None;
+        if best.is_null() {
+            best = *generate_moves(board).get_move(0);
+        }
+        SearchResult { depth_reached: self.search_depth, mv: best, nodes_searched: self.nodes_searched, ttable_hits: self.ttable_hits, nmp_hits: self.nmp_hits }
     }
 
 
+    // lazy smp helper, keeps deepening until the main thread raises stop
+    pub fn search_helper(&mut self, board: &mut Board, start_depth: i32) {
+        let mut depth = start_depth;
+        while !self.stop.load(Ordering::Relaxed) && depth < MAX_HELPER_DEPTH {
+            self.make_search(board, depth);
+            depth += 1;
+        }
+        self.shared_nodes.fetch_add(self.nodes_searched % NODE_BATCH, Ordering::Relaxed);
+    }
+
     pub fn make_search(&mut self, board: &mut Board, depth: i32) -> (i32, Move) {
-        self.generation += 1;
         self.search_depth = depth;
         self.ttable_hits = 0;
         self.nmp_hits = 0;
-        self.killers = KillerTable::new();
-        let mut best_value = i32::MIN;
-        let mut best_move = Move::null();
+        self.seldepth = 0;
+        self.root_side = board.us;
 
-        let mut hash_move = Move::null();
-        if let Some(e) = self.ttable.probe(board.get_hash()) {
-            hash_move = e.best_move;
+        if self.multi_pv > 1 {
+            return self.multi_pv_search(board);
         }
 
-        let mut ordered_moves = OrderedMovesIter::new(hash_move, depth);
-
-        while let Some(mv) = ordered_moves.next(board, &self.history, &self.killers) {
-            board.make_move(&mv);
-            let score = -self.nega_max(board, 1, -INFINITY, INFINITY);
-            if score > best_value {
-                best_move = mv;
-                best_value = score;
-            }
-            board.unmake_move(&mv);
-        }
-        
+        let (best_value, best_move) = match self.prev_score {
+            // a mate or a root without moves leaves nothing for a narrow window to center on
+            Some(prev) if depth >= ASPIRATION_MIN_DEPTH && prev.abs() < MATE_BOUND => self.aspiration_search(board, prev),
+            _ => self.search_root(board, -INFINITY, INFINITY, &[]),
+        };
+        self.prev_score = Some(best_value);
 
         if !self.stop.load(Ordering::Relaxed) {
-            // let mut visited = vec![board.get_hash()];
-            // let pv_string = self.get_pv_string(board, String::new(), &best_move, &mut visited);
-            let pv_string = self.get_pv_string(board, &best_move);
-            println!(
-                "info depth {} cp {} tthits {} nodes searched {} nmp hits {} pv {}",
-                depth, best_value, self.ttable_hits, self.nodes_searched, self.nmp_hits, pv_string
-            )
+            self.root_lines = vec![(best_value, best_move)];
+            self.root_pvs = vec![self.pv_table.line(0).to_vec()];
+            self.report(1, best_value, Bound::Exact, self.root_pvs[0].clone());
         }
         (best_value, best_move)
     }
 
-    fn get_pv_string(&mut self, board: &mut Board, best_mv: &Move) -> String {
-        let mut visited = vec![];
-        let mut mv_stack = vec![*best_mv];
-        let mut s = best_mv.to_str() + " ";
-        board.make_move(best_mv);
+    // each line is searched with a full window among the moves not yet picked, so all scores are exact
+    fn multi_pv_search(&mut self, board: &mut Board) -> (i32, Move) {
+        let mut lines: Vec<(i32, Move, Vec<Move>)> = Vec::with_capacity(self.multi_pv);
+        let mut picked: Vec<Move> = Vec::with_capacity(self.multi_pv);
+        while lines.len() < self.multi_pv {
+            let (value, mv) = self.search_root(board, -INFINITY, INFINITY, &picked);
+            if mv.is_null() || self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            lines.push((value, mv, self.pv_table.line(0).to_vec()));
+            picked.push(mv);
+        }
+        if self.stop.load(Ordering::Relaxed) || lines.is_empty() {
+            return lines.first().map_or((-INFINITY, Move::null()), |line| (line.0, line.1));
+        }
 
+        lines.sort_by_key(|line| std::cmp::Reverse(line.0));
+        self.prev_score = Some(lines[0].0);
+        for (k, (value, _, pv)) in lines.iter().enumerate() {
+            self.report(k + 1, *value, Bound::Exact, pv.clone());
+        }
+        (self.root_lines, self.root_pvs) = lines.into_iter().map(|(value, mv, pv)| ((value, mv), pv)).unzip();
+        self.root_lines[0]
+    }
+
+    // narrow window around the previous iteration's score, widened on fail low/high.
+    // once a bound reaches a mate score the full window is searched and its result taken as is
+    fn aspiration_search(&mut self, board: &mut Board, prev: i32) -> (i32, Move) {
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = prev - delta;
+        let mut beta = prev + delta;
         loop {
-            let hash = board.get_hash();
-            let tt_entry = self.ttable.probe(hash);
-            if tt_entry.is_none() || visited.contains(&hash) {
-                break;
+            let (value, mv) = self.search_root(board, alpha, beta, &[]);
+            if self.stop.load(Ordering::Relaxed) || (alpha == -INFINITY && beta == INFINITY) {
+                return (value, mv);
+            }
+            if value <= alpha {
+                self.report(1, value, Bound::Upper, self.pv_table.line(0).to_vec());
+                alpha = value - delta;
+            } else if value >= beta {
+                self.report(1, value, Bound::Lower, self.pv_table.line(0).to_vec());
+                beta = value + delta;
+            } else {
+                return (value, mv);
+            }
+            if alpha <= -MATE_BOUND || beta >= MATE_BOUND {
+                (alpha, beta) = (-INFINITY, INFINITY);
+            }
+            delta *= 2;
+        }
+    }
+
+    // pvs at the root, only the first move gets the full window
+    fn search_root(&mut self, board: &mut Board, mut alpha: i32, beta: i32, excluded: &[Move]) -> (i32, Move) {
+        let mut best_value = -INFINITY;
+        let mut best_move = Move::null();
+
+        let hash_move = self.root_hash_move(board, excluded);
+        let mut ordered_moves = OrderedMovesIter::new(hash_move, 0, MoveContext::default());
+        let mut moves = Vec::new();
+        while let Some(mv) = ordered_moves.next(board, &self.history, &self.killers) {
+            if !excluded.contains(&mv) && (self.root_moves.is_empty() || self.root_moves.contains(&mv)) {
+                moves.push(mv);
             }
+        }
+        if moves.len() > 2 {
+            let rotation = self.root_rotation % (moves.len() - 1);
+            moves[1..].rotate_left(rotation);
+        }
+        self.pv_table.clear(0);
+        self.stack[0].static_eval = self.evaluator.evaluate(board, get_mg());
+        let single_reply = self.extensions.single_reply && board.is_check() && generate_moves(board).get_count() == 1;
 
-            let mv = tt_entry.unwrap().best_move;
-            s += (mv.to_str() + " ").as_str();
+        for mv in moves {
+            let piece_to = Some((moving_piece(board, &mv), mv.get_target_field()));
             board.make_move(&mv);
-            mv_stack.push(mv);
-            visited.push(hash);
+            // root moves are extended like any other, the line starts with no extensions spent
+            let ext = self.extend(board, &mv, 0, single_reply, false);
+            self.stack[1] = StackEntry { mv, piece_to, extensions: ext, ..Default::default() };
+            let depth_left = self.search_depth - 1 + ext;
+            let score = if best_move.is_null() {
+                -self.nega_max(board, 1, depth_left, -beta, -alpha)
+            } else {
+                let score = -self.nega_max(board, 1, depth_left, -alpha - 1, -alpha);
+                if score > alpha && score < beta {
+                    -self.nega_max(board, 1, depth_left, -beta, -alpha)
+                } else {
+                    score
+                }
+            };
+            board.unmake_move(&mv);
+
+            if score > best_value || best_move.is_null() {
+                best_move = mv;
+                best_value = score;
+                self.pv_table.update(0, mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
         }
+        (best_value, best_move)
+    }
 
-        while let Some(m) = mv_stack.pop() {
-            board.unmake_move(&m);
+    // the root is never stored in the table, so the last completed iteration's best line not yet
+    // picked goes first, the table only helps before the first iteration completes
+    fn root_hash_move(&self, board: &Board, excluded: &[Move]) -> Move {
+        match self.root_lines.iter().find(|(_, mv)| !excluded.contains(mv)) {
+            Some((_, mv)) => *mv,
+            None => self.ttable.probe(board.get_hash()).map_or(Move::null(), |e| e.best_move),
         }
+    }
 
-        s
+    fn report(&self, multipv: usize, value: i32, bound: Bound, pv: Vec<Move>) {
+        if !self.is_main || pv.is_empty() {
+            return;
+        }
+        let time = self.start.elapsed().as_millis();
+        let nodes = self.total_nodes();
+        let info = SearchInfo {
+            depth: self.search_depth,
+            seldepth: self.seldepth,
+            multipv,
+            score: Score::from_search(value),
+            bound,
+            nodes,
+            nps: (nodes as u128 * 1000 / time.max(1)) as u64,
+            time,
+            hashfull: self.ttable.hashfull(),
+            pv,
+        };
+        self.observer.on_info(&info);
     }
 
-    fn nega_max(&mut self, board: &mut Board, depth: i32, mut alpha: i32, beta: i32) -> i32 {
+    fn nega_max(&mut self, board: &mut Board, ply: i32, depth_left: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_table.clear(ply as usize);
         if self.stop.load(Ordering::Relaxed) {
             return alpha;
         }
-        if depth == self.search_depth {
-            return self.quiesce_nega_max(board, depth, alpha, beta);
+        if depth_left <= 0 {
+            return self.quiesce_nega_max(board, ply, 0, alpha, beta);
         }
-        if depth > 0 && self.is_repetition(board) {
-            return 0;
+        if ply > 0 && board.is_draw(ply) {
+            return self.draw_score(board);
+        }
+        if ply as usize >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(board, get_mg());
         }
 
         let org_alpha = alpha;
-        let depth_left = self.search_depth - depth;
-        self.nodes_searched += 1;
+        self.count_node(ply);
         let hash = board.get_hash();
+        let excluded = self.stack[ply as usize].excluded;
         
         let mut hash_move = Move::null();
-        if let Some(e) = self.ttable.probe(hash) {
+        let tt_entry = self.ttable.probe(hash);
+        if let Some(e) = tt_entry {
             hash_move = e.best_move;
             self.ttable_hits += 1;
-            if depth > 0 && e.depth_left >= depth_left {
+            let tt_score = score_from_tt(e.score, ply);
+            if ply > 0 && excluded.is_null() && e.depth_left >= depth_left {
                 match e.entry_type {
                     TTEntryType::Exact => {
-                        return e.score
+                        return tt_score
                     },
-                    TTEntryType::Lower if e.score >= beta => { 
-                        return e.score
+                    TTEntryType::Lower if tt_score >= beta => { 
+                        return tt_score
                     },
-                    TTEntryType::Upper if e.score <= alpha => {
-                        return e.score
+                    TTEntryType::Upper if tt_score <= alpha => {
+                        return tt_score
                     }
                     _ => {}
                 }
             }
         }
 
-        
+
+        let in_check = board.is_check();
+        let pv_node = beta - alpha > 1;
+        let static_eval = if in_check {
+            -INFINITY
+        } else {
+            tt_entry.and_then(|e| e.static_eval).unwrap_or_else(|| self.evaluator.evaluate(board, get_mg()))
+        };
+        self.stack[ply as usize].static_eval = static_eval;
+        let improving = !in_check && ply >= 2 && static_eval > self.stack[ply as usize - 2].static_eval;
+        let can_prune = !pv_node && !in_check && !self.full_width && excluded.is_null() && beta.abs() < MATE_BOUND;
+
+        if can_prune && self.pruning.reverse_futility(depth_left, static_eval, beta, improving) {
+            return static_eval;
+        }
+
+        if can_prune && self.pruning.razor(depth_left, static_eval, alpha) {
+            let q_score = self.quiesce_nega_max(board, ply, 0, alpha, beta);
+            if q_score <= alpha {
+                return q_score;
+            }
+        }
+
         // null move reduction
-        if !self.is_in_zugzwang(board.get_ally_pieces()) && depth_left > NULL_MOVE_RED && !board.is_check() {
+        if !self.full_width && excluded.is_null() && !self.is_in_zugzwang(board.get_ally_pieces()) && depth_left > NULL_MOVE_RED && !board.is_check() {
             board.make_null_mv();
-            let nmr_score = -self.nega_max(board, depth + NULL_MOVE_RED, -beta, -beta + 1);
+            self.stack[ply as usize + 1] = StackEntry { extensions: self.stack[ply as usize].extensions, ..Default::default() };
+            let nmr_score = -self.nega_max(board, ply + 1, depth_left - NULL_MOVE_RED, -beta, -beta + 1);
             board.unmake_null_move();
             if nmr_score >= beta {
                 self.nmp_hits += 1;
@@ -256,23 +564,46 @@ impl Searcher {
             }
         }
 
-        let mut ordered_moves = OrderedMovesIter::new(hash_move, depth);
+        let single_reply = self.extensions.single_reply && board.is_check() && generate_moves(board).get_count() == 1;
+        let singular = excluded.is_null() && tt_entry.is_some_and(|e| self.is_singular(board, ply, depth_left, &e));
+        // the singular and null move searches may have left a line in this row
+        self.pv_table.clear(ply as usize);
+        let context = self.move_context(ply);
+        let mut ordered_moves = OrderedMovesIter::new_excluding(hash_move, ply, context, excluded);
         let mut best_score = i32::MIN;
         let mut best_move = Move::null();
         let mut move_num = 0;
+        let futile = !pv_node && !in_check && !self.full_width && self.pruning.futile(depth_left, static_eval, alpha);
+        let late_move_count = if pv_node || in_check || self.full_width { i32::MAX } else { self.pruning.late_move_count(depth_left, improving) };
+        let mut quiets_tried = MoveList::new();
+        let mut captures_tried = MoveList::new();
 
         while let Some(mv) = ordered_moves.next(board, &self.history, &self.killers) {
+            let piece_to = Some((moving_piece(board, &mv), mv.get_target_field()));
+            let hist_score = if mv.is_non_quiet() { 0 } else { self.history.quiet_score(board, &mv, &context) };
             board.make_move(&mv);
-            let lmr = self.can_reduce(board, depth_left, depth, &mv, move_num);
-            let score = if lmr > 0 {
-                let temp_score = -self.nega_max(board, depth + 1 + lmr, -beta, -alpha);
-                if temp_score > alpha {
-                    -self.nega_max(board, depth + 1, -beta, -alpha)
-                } else {
-                    temp_score
-                }
+            let quiet = !mv.is_capture() && !mv.is_promotion() && !board.is_check();
+            if quiet && move_num > 0 && best_score > -MATE_BOUND && (futile || move_num >= late_move_count) {
+                board.unmake_move(&mv);
+                move_num += 1;
+                continue;
+            }
+            let ext = self.extend(board, &mv, ply, single_reply, singular && mv == hash_move);
+            self.stack[ply as usize + 1] = StackEntry { mv, piece_to, extensions: self.stack[ply as usize].extensions + ext, ..Default::default() };
+            let new_depth = depth_left - 1 + ext;
+            let lmr = if ext == 0 && !self.full_width { self.can_reduce(board, depth_left, ply, &mv, move_num, hist_score) } else { 0 };
+            let score = if move_num == 0 {
+                -self.nega_max(board, ply + 1, new_depth, -beta, -alpha)
             } else {
-                    -self.nega_max(board, depth + 1, -beta, -alpha)
+                // zero window first, the full window only when the move beats alpha
+                let mut score = -self.nega_max(board, ply + 1, new_depth - lmr, -alpha - 1, -alpha);
+                if score > alpha && lmr > 0 {
+                    score = -self.nega_max(board, ply + 1, new_depth, -alpha - 1, -alpha);
+                }
+                if score > alpha && score < beta {
+                    score = -self.nega_max(board, ply + 1, new_depth, -beta, -alpha);
+                }
+                score
             };
             
             board.unmake_move(&mv);
@@ -280,105 +611,321 @@ impl Searcher {
             move_num += 1;
 
             if score > best_score {
+                if score > alpha {
+                    self.pv_table.update(ply as usize, mv);
+                }
                 best_score = score;
                 alpha = alpha.max(score);
                 best_move = mv;
             }
             if score >= beta {
-                self.killers.update(depth, mv);
-                self.history.add(&mv, depth_left);
+                if mv.is_non_quiet() {
+                    self.history.update_captures(board, Some(&mv), captures_tried.as_slice(), depth_left);
+                } else {
+                    self.killers.update(ply, mv);
+                    self.history.update_quiets(board, &mv, quiets_tried.as_slice(), &context, depth_left);
+                    self.history.update_captures(board, None, captures_tried.as_slice(), depth_left);
+                }
             }
             if alpha >= beta { break } 
+            if mv.is_non_quiet() {
+                captures_tried.push_move(mv);
+            } else {
+                quiets_tried.push_move(mv);
+            }
         }
 
         //no moves
         if best_move.is_null() {
-           return if board.is_check() { -MATE+depth } else { 0 }
+            if !excluded.is_null() {
+                return alpha;
+            }
+            return if board.is_check() { -MATE+ply } else { self.draw_score(board) }
+        }
+
+        // the excluded search saw only part of the moves, its result must not replace the real entry,
+        // and a stopped child returned alpha, which is no score worth keeping either
+        if !excluded.is_null() || self.stop.load(Ordering::Relaxed) {
+            return best_score;
         }
 
        let tt_type = if best_score > org_alpha && best_score < beta {TTEntryType::Exact}
                                       else if best_score >= beta {TTEntryType::Lower}
                                       else {TTEntryType::Upper};
-        self.store_tt(hash, best_score, depth_left, tt_type, best_move);
+        self.store_tt(hash, score_to_tt(best_score, ply), depth_left, tt_type, best_move, (!in_check).then_some(static_eval));
         best_score
     }
 
-    fn store_tt(&mut self, hash: u64, score: i32, depth_left: i32, tt_type: TTEntryType, best_move: Move) {
+    // the hash move is singular when every other move fails low against a margin below its score
+    fn is_singular(&mut self, board: &mut Board, ply: i32, depth_left: i32, entry: &Entry) -> bool {
+        let tt_score = score_from_tt(entry.score, ply);
+        if !self.extensions.singular
+            || ply == 0
+            || depth_left < SINGULAR_MIN_DEPTH
+            || entry.best_move.is_null()
+            || entry.entry_type == TTEntryType::Upper
+            || entry.depth_left < depth_left - SINGULAR_TT_DEPTH_MARGIN
+            || tt_score.abs() > MATE_BOUND
+        {
+            return false;
+        }
+        let singular_beta = tt_score - SINGULAR_MARGIN_PER_DEPTH * depth_left;
+        self.stack[ply as usize].excluded = entry.best_move;
+        let score = self.nega_max(board, ply, (depth_left - 1) / 2, singular_beta - 1, singular_beta);
+        self.stack[ply as usize].excluded = Move::null();
+        score < singular_beta
+    }
+
+    // piece and target of the last two moves on the line, the null move leaves them empty
+    fn move_context(&self, ply: i32) -> MoveContext {
+        let ply = ply as usize;
+        let two_back = if ply >= 1 { self.stack[ply - 1].piece_to } else { None };
+        MoveContext { prev: [self.stack[ply].piece_to, two_back] }
+    }
+
+    // extensions on a single line are capped at half the root depth so check sequences can't run away
+    fn extend(&self, board: &Board, mv: &Move, ply: i32, single_reply: bool, singular: bool) -> i32 {
+        let used = self.stack[ply as usize].extensions;
+        if used * 2 >= self.search_depth {
+            return 0;
+        }
+        let prev = self.stack[ply as usize].mv;
+        self.extensions.extension(board, mv, &prev, single_reply, singular)
+    }
+
+    fn store_tt(&self, hash: u64, score: i32, depth_left: i32, tt_type: TTEntryType, best_move: Move, static_eval: Option<i32>) {
         let entry = Entry {
             key: hash,
             depth_left,
             entry_type: tt_type,
-            generation: self.generation,
+            generation: self.ttable.generation(),
             score,
-            best_move
+            best_move,
+            static_eval
         };
         self.ttable.store(entry);
     }
 
-    fn is_repetition(&self, board: &Board) -> bool {
-        let hash = board.get_hash();
-        let hash_stack = board.get_hash_stack();
-        hash_stack.contains(&hash)
+    // a positive contempt makes the side to move at the root avoid draws
+    fn draw_score(&self, board: &Board) -> i32 {
+        if board.us == self.root_side { -self.contempt } else { self.contempt }
     }
 
     fn is_in_zugzwang(&self, pieces: &PieceSet) -> bool {
         pieces.get_orthogonals() | pieces.get_diagonals() | pieces.get_knights() == 0
     }
 
-    fn quiesce_nega_max(&mut self, board: &mut Board, depth: i32, mut alpha: i32, beta: i32) -> i32 {
+    // qs_ply counts plies since the main search ended, quiet checks are only tried on the first one
+    fn quiesce_nega_max(&mut self, board: &mut Board, ply: i32, qs_ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_table.clear(ply as usize);
         if self.stop.load(Ordering::Relaxed) {
             return alpha;
         }
-        self.nodes_searched += 1;
-        let moves = generate_moves(board);
-        if moves.get_count() == 0 {
-            if board.is_check() {
-                return -MATE+depth;
-            } else {
-                return 0;
+        if ply as usize >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(board, get_mg());
+        }
+        self.count_node(ply);
+        let hash = board.get_hash();
+        let org_alpha = alpha;
+
+        let tt_entry = self.ttable.probe(hash);
+        if let Some(e) = tt_entry {
+            self.ttable_hits += 1;
+            let tt_score = score_from_tt(e.score, ply);
+            match e.entry_type {
+                TTEntryType::Exact => return tt_score,
+                TTEntryType::Lower if tt_score >= beta => return tt_score,
+                TTEntryType::Upper if tt_score <= alpha => return tt_score,
+                _ => {}
             }
         }
-        let mut best_value = self.evaluator.evaluate(board, get_mg());
-        if best_value >= beta {
-            return best_value;
+
+        if board.is_check() {
+            let hash_move = tt_entry.map_or(Move::null(), |e| e.best_move);
+            return self.quiesce_evasions(board, ply, qs_ply, alpha, beta, hash_move);
+        }
+
+        let moves = generate_moves(board);
+        if moves.get_count() == 0 {
+            return self.draw_score(board);
         }
-        if alpha < best_value {
-            alpha = best_value
+        let stand_pat = tt_entry.and_then(|e| e.static_eval).unwrap_or_else(|| self.evaluator.evaluate(board, get_mg()));
+        if stand_pat >= beta {
+            if !self.stop.load(Ordering::Relaxed) {
+                self.store_tt(hash, score_to_tt(stand_pat, ply), 0, TTEntryType::Lower, Move::null(), Some(stand_pat));
+            }
+            return stand_pat;
         }
+        let mut best_value = stand_pat;
+        let mut best_move = Move::null();
+        alpha = alpha.max(stand_pat);
 
-        let mut ordered_moves = QuiesceOrderedMovesIter::new(moves, board, &self.history, &self.killers, depth);
+        let mut ordered_moves = QuiesceOrderedMovesIter::new(&moves, board, &self.history, &self.killers, ply);
         while let Some(mv) = ordered_moves.next() {
+            // delta pruning, even winning the piece outright would not reach alpha
+            if !mv.is_promotion() && stand_pat + captured_value(board, &mv) + DELTA_MARGIN <= alpha {
+                continue;
+            }
             board.make_move(&mv);
-            let score = -self.quiesce_nega_max(board, depth + 1, -beta, -alpha);
+            let score = -self.quiesce_nega_max(board, ply + 1, qs_ply + 1, -beta, -alpha);
             board.unmake_move(&mv);
             if score > best_value {
+                if score > alpha {
+                    self.pv_table.update(ply as usize, mv);
+                }
                 best_value = score;
+                best_move = mv;
+                alpha = alpha.max(score);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        // quiet checks that don't hang the piece, so mate threats don't fall off the horizon
+        if qs_ply == 0 && self.qsearch_checks && alpha < beta {
+            for mv in moves.iter().filter(|mv| !mv.is_capture() && !mv.is_promotion()) {
+                if see(board, mv) < 0 {
+                    continue;
+                }
+                board.make_move(mv);
+                if !board.is_check() {
+                    board.unmake_move(mv);
+                    continue;
+                }
+                let score = -self.quiesce_nega_max(board, ply + 1, qs_ply + 1, -beta, -alpha);
+                board.unmake_move(mv);
+                if score > best_value {
+                    if score > alpha {
+                        self.pv_table.update(ply as usize, *mv);
+                    }
+                    best_value = score;
+                    best_move = *mv;
+                    alpha = alpha.max(score);
+                }
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        // a stopped child returned alpha, the value is not worth keeping
+        if self.stop.load(Ordering::Relaxed) {
+            return best_value;
+        }
+        let tt_type = if best_value >= beta { TTEntryType::Lower } else if best_value > org_alpha { TTEntryType::Exact } else { TTEntryType::Upper };
+        self.store_tt(hash, score_to_tt(best_value, ply), 0, tt_type, best_move, Some(stand_pat));
+        best_value
+    }
+
+    // no stand pat in check, every evasion is searched
+    fn quiesce_evasions(&mut self, board: &mut Board, ply: i32, qs_ply: i32, mut alpha: i32, beta: i32, hash_move: Move) -> i32 {
+        let org_alpha = alpha;
+        let mut best_value = -INFINITY;
+        let mut best_move = Move::null();
+        let mut ordered_moves = OrderedMovesIter::new(hash_move, ply, MoveContext::default());
+        while let Some(mv) = ordered_moves.next(board, &self.history, &self.killers) {
+            board.make_move(&mv);
+            let score = -self.quiesce_nega_max(board, ply + 1, qs_ply + 1, -beta, -alpha);
+            board.unmake_move(&mv);
+            if score > best_value {
                 if score > alpha {
-                    alpha = score;
+                    self.pv_table.update(ply as usize, mv);
                 }
+                best_value = score;
+                best_move = mv;
+                alpha = alpha.max(score);
             }
-            if score >= beta {
-                return best_value;
+            if alpha >= beta {
+                break;
             }
         }
+        if best_move.is_null() {
+            return -MATE + ply;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            return best_value;
+        }
+        let tt_type = if best_value >= beta { TTEntryType::Lower } else if best_value > org_alpha { TTEntryType::Exact } else { TTEntryType::Upper };
+        self.store_tt(board.get_hash(), score_to_tt(best_value, ply), 0, tt_type, best_move, None);
         best_value
     }
 
+    // the second move of best_mv's line, sent along with bestmove for pondering
+    pub fn ponder_move(&self, best_mv: &Move) -> Option<Move> {
+        let line = self.root_lines.iter().position(|(_, mv)| mv == best_mv)?;
+        self.root_pvs[line].get(1).copied()
+    }
+
+    // reaching the node limit stops the search like a stop command would,
+    // but only once an iteration finished so there is always a move to play
+    fn count_node(&mut self, ply: i32) {
+        self.nodes_searched += 1;
+        if self.nodes_searched.is_multiple_of(NODE_BATCH) {
+            self.shared_nodes.fetch_add(NODE_BATCH, Ordering::Relaxed);
+        }
+        self.seldepth = self.seldepth.max(ply);
+        if self.nodes_searched >= self.node_limit && !self.root_lines.is_empty() {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        if self.nodes_searched.is_multiple_of(TIME_CHECK_NODES) {
+            if let Some(limit) = self.time_limit {
+                if self.clock.elapsed().is_some_and(|t| t >= limit) {
+                    self.stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
     pub fn get_nodes_searched(&self) -> u64 {
         self.nodes_searched
     }
 
-    fn can_reduce(&self, board: &Board, depth_left: i32, depth: i32, mv: &Move, move_num: i32) -> i32 {
-        if depth_left > 3 && !mv.is_non_quiet() &&  move_num > 3 && !self.killers.is_killer(depth, mv) && board.get_checkers() == 0 {
+    // this searcher's nodes and those the helpers added so far
+    pub fn total_nodes(&self) -> u64 {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes_searched % NODE_BATCH
+    }
+
+    fn reset_nodes(&mut self) {
+        self.nodes_searched = 0;
+        if self.is_main {
+            self.shared_nodes.store(0, Ordering::Relaxed);
+        }
+    }
+
+    // moves with good history are reduced less, moves with bad history more
+    fn can_reduce(&self, board: &Board, depth_left: i32, ply: i32, mv: &Move, move_num: i32, hist_score: i32) -> i32 {
+        if depth_left > 3 && !mv.is_non_quiet() &&  move_num > 3 && !self.killers.is_killer(ply, mv) && board.get_checkers() == 0 {
             let depth_capped = std::cmp::min(depth_left, 63) as usize;
-            let red_depth = self.lmr_table[move_num as usize][depth_capped];
-            std::cmp::min(red_depth, depth_left - 1)
+            let red_depth = self.lmr_table[move_num as usize][depth_capped] - hist_score / LMR_HISTORY_DIVISOR;
+            red_depth.clamp(0, depth_left - 1)
         } else {
             0
         }
     }
 }
 
+// mate scores are stored relative to the node, so they stay valid when probed at another ply
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score + ply
+    } else if score < -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score - ply
+    } else if score < -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
 fn compute_lmr_table() -> [[i32; 64]; 218] {
     let mut depth = 0;
     let mut lmr_arr = [[0; 64]; 218];
@@ -399,3 +946,199 @@ fn compute_lmr_table() -> [[i32; 64]; 218] {
     }
     lmr_arr
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::{score_from_tt, score_to_tt, Searcher, MATE, NODE_BATCH};
+    use crate::fen_parsing::parse_fen::parse_fen;
+    use crate::moving::move_generation::generate_moves;
+    use crate::search::clock::SearchClock;
+    use crate::search::observer::{Bound, Score, SearchInfo};
+    use crate::search::transposition::{Entry, TTEntryType};
+    use crate::moving::mv::Move;
+    use crate::tests::common::{Recorder, ITALIAN_GAME};
+
+    #[test]
+    fn should_keep_mate_distance_when_probed_from_another_ply() {
+        // mate found 7 plies from the root, stored at ply 4 and probed at ply 2
+        let stored = score_to_tt(MATE - 7, 4);
+        assert_eq!(score_from_tt(stored, 2), MATE - 5);
+        let stored = score_to_tt(-MATE + 7, 4);
+        assert_eq!(score_from_tt(stored, 2), -MATE + 5);
+    }
+
+    fn search_nodes(nodes: u64) -> (u64, Move) {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let mut searcher = Searcher::new();
+        let stop = Arc::new(AtomicBool::new(false));
+        searcher.set_node_limit(Some(nodes));
+        searcher.prepare_search(stop.clone());
+        let mut best = Move::null();
+        for depth in 1..64 {
+            let (_, mv) = searcher.make_search(&mut board, depth);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            best = mv;
+        }
+        (searcher.get_nodes_searched(), best)
+    }
+
+    #[test]
+    fn should_stop_exactly_at_node_limit() {
+        let (nodes, best) = search_nodes(20_000);
+        assert_eq!(nodes, 20_000);
+        assert!(search_nodes(20_000).1 == best);
+    }
+
+    #[test]
+    fn should_report_nodes_of_helpers_along_with_its_own() {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let mut main = Searcher::new();
+        main.set_observer(recorder.clone());
+        let mut helper = Searcher::new_shared(main.ttable.clone(), false);
+        helper.set_shared_nodes(main.shared_nodes());
+        let stop = Arc::new(AtomicBool::new(false));
+        main.prepare_search(stop.clone());
+        helper.prepare_search(stop);
+        helper.make_search(&mut board, 5);
+        main.make_search(&mut board, 3);
+
+        // helpers hand in their nodes in whole batches while they search
+        let helper_nodes = helper.get_nodes_searched() / NODE_BATCH * NODE_BATCH;
+        let info = recorder.infos.lock().unwrap().last().unwrap().clone();
+        assert!(helper_nodes > 0);
+        assert_eq!(info.nodes, main.get_nodes_searched() + helper_nodes);
+    }
+
+    #[test]
+    fn should_stop_itself_at_time_limit() {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let mut searcher = Searcher::new();
+        searcher.prepare_search(Arc::new(AtomicBool::new(false)));
+        searcher.set_time_limit(SearchClock::default(), Some(50));
+        let start = Instant::now();
+        // one iteration this deep would take far longer than the limit
+        searcher.make_search(&mut board, 30);
+        assert!(start.elapsed().as_millis() < 500);
+    }
+
+    #[test]
+    fn should_leave_aspiration_windows_when_there_is_no_move() {
+        let mut board = parse_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        let mut searcher = Searcher::new();
+        assert!(searcher.search_to_depth(&mut board, 6).mv.is_null());
+    }
+
+    #[test]
+    fn should_search_the_last_iterations_best_lines_first_at_the_root() {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(2);
+        searcher.search_to_depth(&mut board, 4);
+        searcher.ttable.clear();
+
+        let lines = searcher.get_root_lines().to_vec();
+        assert!(searcher.root_hash_move(&board, &[]) == lines[0].1);
+        assert!(searcher.root_hash_move(&board, &[lines[0].1]) == lines[1].1);
+    }
+
+    #[test]
+    fn should_stop_extending_once_half_the_depth_is_spent() {
+        let mut board = parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let check = *generate_moves(&board).iter().find(|mv| mv.to_str() == "a1a8").unwrap();
+        board.make_move(&check);
+        let mut searcher = Searcher::new();
+        searcher.search_depth = 6;
+        searcher.stack[1].extensions = 2;
+        assert_eq!(searcher.extend(&board, &check, 1, false, false), 1);
+        searcher.stack[1].extensions = 3;
+        assert_eq!(searcher.extend(&board, &check, 1, false, false), 0);
+    }
+
+    #[test]
+    fn should_report_multi_pv_lines_best_first_with_exact_scores() {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let mut searcher = Searcher::new();
+        searcher.set_observer(recorder.clone());
+        searcher.set_multi_pv(3);
+        let result = searcher.search_to_depth(&mut board, 5);
+
+        let lines = searcher.get_root_lines();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.windows(2).all(|pair| pair[0].0 >= pair[1].0 && pair[0].1 != pair[1].1));
+        assert!(lines[0].1 == result.mv);
+        let infos = recorder.infos.lock().unwrap();
+        let last: Vec<&SearchInfo> = infos.iter().filter(|info| info.depth == 5).collect();
+        assert_eq!(last.iter().map(|info| info.multipv).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(infos.iter().all(|info| info.bound == Bound::Exact));
+        for (info, (score, mv)) in last.iter().zip(lines) {
+            assert!(info.score == Score::from_search(*score) && info.pv[0] == *mv);
+        }
+    }
+
+    #[test]
+    fn should_pick_best_move_among_search_moves_only() {
+        // Qxd8 wins the queen, searchmoves leave it out
+        let mut board = parse_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let allowed: Vec<Move> = generate_moves(&board).iter()
+            .filter(|mv| ["e1e2", "e1f2", "d1a4"].contains(&mv.to_str().as_str()))
+            .copied()
+            .collect();
+        let mut searcher = Searcher::new();
+        searcher.set_root_moves(allowed.clone());
+        for depth in 1..=4 {
+            assert!(allowed.contains(&searcher.search_flat(&mut board, depth).mv));
+        }
+        searcher.set_multi_pv(5);
+        searcher.search_to_depth(&mut board, 3);
+        let picked: Vec<Move> = searcher.get_root_lines().iter().map(|(_, mv)| *mv).collect();
+        assert_eq!(picked.len(), allowed.len());
+        assert!(picked.iter().all(|mv| allowed.contains(mv)));
+    }
+
+    #[test]
+    fn should_keep_the_real_tt_entry_after_an_excluded_move_search() {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let mut searcher = Searcher::new();
+        let best_move = *generate_moves(&board).iter().find(|mv| mv.to_str() == "f3g5").unwrap();
+        let entry = Entry {
+            key: board.get_hash(),
+            depth_left: 8,
+            score: 50,
+            // old enough that any store for this position would replace it
+            generation: searcher.ttable.generation() - 5,
+            entry_type: TTEntryType::Lower,
+            best_move,
+            static_eval: Some(20),
+        };
+        searcher.ttable.store(entry);
+        searcher.search_depth = 9;
+        searcher.is_singular(&mut board, 1, 8, &entry);
+
+        let after = searcher.ttable.probe(board.get_hash()).unwrap();
+        assert_eq!((after.depth_left, after.score, after.static_eval), (8, 50, Some(20)));
+        assert!(after.entry_type == TTEntryType::Lower && after.best_move == best_move);
+        assert!(searcher.stack[1].excluded.is_null());
+    }
+
+    #[test]
+    fn should_collect_legal_pv_from_search() {
+        let mut board = parse_fen(ITALIAN_GAME).unwrap();
+        let mut searcher = Searcher::new();
+        let result = searcher.search_to_depth(&mut board, 8);
+        let pv = searcher.get_pv();
+        assert!(pv.len() >= 2 && pv[0] == result.mv);
+        assert!(searcher.ponder_move(&result.mv) == Some(pv[1]));
+        for mv in pv {
+            assert!(generate_moves(&board).iter().any(|legal| *legal == mv));
+            board.make_move(&mv);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// time since the search started, or since ponderhit when pondering
+#[derive(Clone)]
+pub struct SearchClock {
+    start: Instant,
+    ponder: Arc<AtomicBool>,
+}
+
+impl Default for SearchClock {
+    fn default() -> Self {
+        SearchClock::new(Arc::new(AtomicBool::new(false)))
+    }
+}
+
+impl SearchClock {
+    pub fn new(ponder: Arc<AtomicBool>) -> Self {
+        SearchClock { start: Instant::now(), ponder }
+    }
+
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    // no limit applies while pondering, so there is no elapsed time yet
+    pub fn elapsed(&mut self) -> Option<u128> {
+        if self.ponder.load(Ordering::Relaxed) {
+            self.start = Instant::now();
+            return None;
+        }
+        Some(self.start.elapsed().as_millis())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::constants::{RANK2, RANK7, WHITE};
+use crate::moving::mv::Move;
+use crate::position::board::Board;
+
+// each extension can be switched separately to measure it with the wac harness,
+// recaptures are off by default since they blow up the tree more than they gain
+#[derive(Clone, Copy)]
+pub struct ExtensionConfig {
+    pub check: bool,
+    pub single_reply: bool,
+    pub recapture: bool,
+    pub passed_pawn: bool,
+    pub singular: bool,
+}
+
+impl Default for ExtensionConfig {
+    fn default() -> Self {
+        ExtensionConfig { check: true, single_reply: true, recapture: false, passed_pawn: true, singular: true }
+    }
+}
+
+impl ExtensionConfig {
+    // board is the position after mv, prev is the move that led to the position before it.
+    // single_reply and singular are found by the search, each is gated by its own switch
+    pub fn extension(&self, board: &Board, mv: &Move, prev: &Move, single_reply: bool, singular: bool) -> i32 {
+        let extend = (self.check && board.is_check())
+            || (self.single_reply && single_reply)
+            || (self.singular && singular)
+            || (self.recapture && is_recapture(mv, prev))
+            || (self.passed_pawn && is_pawn_push_to_seventh(board, mv));
+        extend as i32
+    }
+}
+
+fn is_recapture(mv: &Move, prev: &Move) -> bool {
+    !prev.is_null() && prev.is_capture() && mv.is_capture() && prev.get_target_field() == mv.get_target_field()
+}
+
+// a pawn on the seventh has no enemy pawns left in front of it, so it is always passed
+fn is_pawn_push_to_seventh(board: &Board, mv: &Move) -> bool {
+    let mover = board.enemy;
+    let seventh = if mover == WHITE { RANK7 } else { RANK2 };
+    board.get_pieces(mover).get_pawns() & mv.get_target_bb() & seventh != 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_pawn_push_to_seventh, is_recapture, ExtensionConfig};
+    use crate::fen_parsing::parse_fen::parse_fen;
+    use crate::moving::move_generation::generate_moves;
+    use crate::moving::mv::Move;
+    use crate::position::board::Board;
+
+    // the position after mv_s was played from fen
+    fn play(fen: &str, mv_s: &str) -> (Board, Move) {
+        let mut board = parse_fen(fen).unwrap();
+        let mv = *generate_moves(&board).iter().find(|mv| mv.to_str() == mv_s).unwrap();
+        board.make_move(&mv);
+        (board, mv)
+    }
+
+    #[test]
+    fn should_spot_recaptures_on_the_same_square() {
+        let capture = Move::new_capture(28, 35);
+        assert!(is_recapture(&Move::new_capture(42, 35), &capture));
+        assert!(!is_recapture(&Move::new_capture(42, 36), &capture));
+        assert!(!is_recapture(&Move::new_quiet(42, 35), &capture));
+        assert!(!is_recapture(&Move::new_capture(42, 35), &Move::new_quiet(28, 35)));
+        assert!(!is_recapture(&Move::new_capture(42, 35), &Move::null()));
+    }
+
+    #[test]
+    fn should_spot_pawn_pushes_to_the_seventh_for_both_sides() {
+        let (board, mv) = play("k7/8/4P3/8/8/8/8/4K3 w - - 0 1", "e6e7");
+        assert!(is_pawn_push_to_seventh(&board, &mv));
+        let (board, mv) = play("4k3/8/8/8/8/3p4/8/K7 b - - 0 1", "d3d2");
+        assert!(is_pawn_push_to_seventh(&board, &mv));
+        let (board, mv) = play("k7/8/8/4P3/8/8/8/4K3 w - - 0 1", "e5e6");
+        assert!(!is_pawn_push_to_seventh(&board, &mv));
+        let (board, mv) = play("k7/8/8/8/8/8/1R6/4K3 w - - 0 1", "b2b7");
+        assert!(!is_pawn_push_to_seventh(&board, &mv));
+    }
+
+    #[test]
+    fn should_extend_only_for_switched_on_conditions() {
+        let all = ExtensionConfig { recapture: true, ..Default::default() };
+        let none = ExtensionConfig { check: false, single_reply: false, recapture: false, passed_pawn: false, singular: false };
+        let (board, mv) = play("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8");
+        assert_eq!(all.extension(&board, &mv, &Move::null(), false, false), 1);
+        assert_eq!(none.extension(&board, &mv, &Move::null(), false, false), 0);
+
+        let (board, mv) = play("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1b1");
+        assert_eq!(all.extension(&board, &mv, &Move::null(), false, false), 0);
+        for (single_reply, singular) in [(true, false), (false, true)] {
+            assert_eq!(all.extension(&board, &mv, &Move::null(), single_reply, singular), 1);
+            assert_eq!(none.extension(&board, &mv, &Move::null(), single_reply, singular), 0);
+        }
+        // each switch gates its own condition only
+        let no_single_reply = ExtensionConfig { single_reply: false, ..Default::default() };
+        assert_eq!(no_single_reply.extension(&board, &mv, &Move::null(), false, true), 1);
+        let no_singular = ExtensionConfig { singular: false, ..Default::default() };
+        assert_eq!(no_singular.extension(&board, &mv, &Move::null(), true, false), 1);
+    }
+}
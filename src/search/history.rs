@@ -1,21 +1,148 @@
+use crate::constants::{NONE, PAWN};
 use crate::moving::mv::Move;
+use crate::position::board::Board;
+
+// every table is updated with gravity, so values stay within +-MAX_HISTORY
+const MAX_HISTORY: i32 = 16_384;
+const MAX_BONUS: i32 = 1_200;
+const PIECES: usize = 12;
+const CONT_SIZE: usize = PIECES * 64 * PIECES * 64;
+const CAPTURE_SIZE: usize = PIECES * 64 * 6;
+
+// (colored piece, target square) of an earlier move on the line, None after a null move or at the root
+pub type PieceTo = Option<(usize, usize)>;
+
+// the one and two ply earlier moves the continuation history is indexed by
+#[derive(Clone, Copy, Default)]
+pub struct MoveContext {
+    pub prev: [PieceTo; 2],
+}
 
 pub struct HistoryTable {
-    table: Box<[i32; 1 << 16]>
+    butterfly: Box<[[[i32; 64]; 64]; 2]>,
+    counter_moves: Box<[[Move; 64]; PIECES]>,
+    continuation: Box<[i32]>,
+    capture: Box<[i32]>,
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HistoryTable {
     pub fn new() -> Self {
-        HistoryTable { table: Box::new([0; 1<< 16]) }
+        HistoryTable {
+            butterfly: Box::new([[[0; 64]; 64]; 2]),
+            counter_moves: Box::new([[Move::null(); 64]; PIECES]),
+            continuation: vec![0; CONT_SIZE].into_boxed_slice(),
+            capture: vec![0; CAPTURE_SIZE].into_boxed_slice(),
+        }
+    }
+
+    pub fn quiet_score(&self, board: &Board, mv: &Move, ctx: &MoveContext) -> i32 {
+        let piece = moving_piece(board, mv);
+        let to = mv.get_target_field();
+        let mut score = self.butterfly[board.us][mv.get_start_field()][to];
+        for prev in ctx.prev.iter().flatten() {
+            score += self.continuation[cont_index(*prev, (piece, to))];
+        }
+        score
+    }
+
+    pub fn capture_score(&self, board: &Board, mv: &Move) -> i32 {
+        self.capture[capture_index(board, mv)]
+    }
+
+    pub fn counter_move(&self, ctx: &MoveContext) -> Move {
+        match ctx.prev[0] {
+            Some((piece, to)) => self.counter_moves[piece][to],
+            None => Move::null(),
+        }
+    }
+
+    // the cutoff move gets a bonus, the quiets tried before it a malus
+    pub fn update_quiets(&mut self, board: &Board, best: &Move, tried: &[Move], ctx: &MoveContext, depth_left: i32) {
+        let bonus = history_bonus(depth_left);
+        self.update_quiet(board, best, ctx, bonus);
+        for mv in tried.iter().filter(|mv| *mv != best) {
+            self.update_quiet(board, mv, ctx, -bonus);
+        }
+        if let Some((piece, to)) = ctx.prev[0] {
+            self.counter_moves[piece][to] = *best;
+        }
     }
 
-    pub fn get_val(&self, mv: &Move) -> i32 {
-        let index = mv.get_value() as usize;
-        self.table[index]
+    pub fn update_captures(&mut self, board: &Board, best: Option<&Move>, tried: &[Move], depth_left: i32) {
+        let bonus = history_bonus(depth_left);
+        if let Some(best) = best {
+            apply_gravity(&mut self.capture[capture_index(board, best)], bonus);
+        }
+        for mv in tried.iter().filter(|mv| Some(*mv) != best) {
+            apply_gravity(&mut self.capture[capture_index(board, mv)], -bonus);
+        }
     }
 
-    pub fn add(&mut self, mv: &Move, depth_left: i32) {
-        let index = mv.get_value() as usize;
-        self.table[index] += (depth_left * depth_left) + 1;
+    // keeps what was learned in earlier searches but lets the new position take over quickly
+    pub fn age(&mut self) {
+        for v in self.butterfly.iter_mut().flatten().flatten() {
+            *v /= 2;
+        }
+        for v in self.continuation.iter_mut().chain(self.capture.iter_mut()) {
+            *v /= 2;
+        }
+    }
+
+    fn update_quiet(&mut self, board: &Board, mv: &Move, ctx: &MoveContext, bonus: i32) {
+        let piece = moving_piece(board, mv);
+        let to = mv.get_target_field();
+        apply_gravity(&mut self.butterfly[board.us][mv.get_start_field()][to], bonus);
+        for prev in ctx.prev.iter().flatten() {
+            apply_gravity(&mut self.continuation[cont_index(*prev, (piece, to))], bonus);
+        }
+    }
+}
+
+// colored piece index of the piece making mv, board is the position before it
+pub fn moving_piece(board: &Board, mv: &Move) -> usize {
+    board.us * 6 + board.get_ally_pieces().get_piece_at(mv.get_start_field())
+}
+
+fn history_bonus(depth_left: i32) -> i32 {
+    (16 * depth_left * depth_left + 32 * depth_left).min(MAX_BONUS)
+}
+
+fn apply_gravity(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+}
+
+fn cont_index(prev: (usize, usize), cur: (usize, usize)) -> usize {
+    ((prev.0 * 64 + prev.1) * PIECES + cur.0) * 64 + cur.1
+}
+
+fn capture_index(board: &Board, mv: &Move) -> usize {
+    let to = mv.get_target_field();
+    let captured = if mv.is_en_passant() { PAWN } else { board.get_enemy_pieces().get_piece_at(to) };
+    // promotions without capture land on an empty square
+    let captured = if captured == NONE { PAWN } else { captured };
+    (moving_piece(board, mv) * 64 + to) * 6 + captured
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply_gravity, MAX_HISTORY};
+
+    #[test]
+    fn should_keep_history_bounded_under_repeated_bonuses() {
+        let mut entry = 0;
+        for _ in 0..10_000 {
+            apply_gravity(&mut entry, 1_200);
+        }
+        assert!(entry <= MAX_HISTORY);
+        for _ in 0..10_000 {
+            apply_gravity(&mut entry, -1_200);
+        }
+        assert!(entry >= -MAX_HISTORY);
     }
 }
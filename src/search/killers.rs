@@ -16,10 +16,10 @@ impl KillerTable {
     pub fn update(&mut self, ply: i32, mv: Move) {
         let ply = std::cmp::min(ply as usize, MAX_PLY - 1);
 
-        if self.table[ply][0].is_null() {
+        if self.table[ply][0] != mv {
             self.table[ply][1] = self.table[ply][0];
+            self.table[ply][0] = mv;
         }
-        self.table[ply][0] = mv;
     }
 
     pub fn is_killer(&self, ply: i32, mv: &Move) -> bool {
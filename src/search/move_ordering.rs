@@ -1,19 +1,21 @@
-use crate::bitboard_helpers::{flip_color, isolate_lsb};
-use crate::constants::{BISHOP, KING, KNIGHT, PAWN, QUEEN, ROOK, WHITE};
 use crate::evaluation::piece_values::MIDGAME_PIECE_VALUES;
-use crate::moving::move_generation::{generate_moves, get_mg, is_legal};
+use crate::moving::move_generation::{generate_moves, is_legal};
 use crate::moving::move_list::MoveList;
 use crate::moving::mv::Move;
 use crate::position::board::Board;
-use crate::position::piece_set::PieceSet;
-use crate::search::history::HistoryTable;
+use crate::search::history::{HistoryTable, MoveContext};
 use crate::search::killers::KillerTable;
+use crate::search::see::see;
+
+const CAPTURE_HISTORY_DIVISOR: i32 = 64;
 
 pub struct OrderedMovesIter {
     moves: Vec<ClassifiedMove>,
     phase: MoveOrderingPhase,
     pv_node: Move,
+    excluded: Move,
     ply: i32,
+    context: MoveContext,
 }
 
 enum MoveOrderingPhase {
@@ -22,6 +24,7 @@ enum MoveOrderingPhase {
     Promotions,
     WinningCaptures,
     KillerMoves,
+    CounterMove,
     EqualCaptures,
     Quiet,
     LosingCaptures,
@@ -30,13 +33,18 @@ enum MoveOrderingPhase {
 
 
 impl OrderedMovesIter {
-    pub fn new(pv_node: Move, ply: i32) -> Self {
-        OrderedMovesIter { moves: Vec::new(), phase: MoveOrderingPhase::PvNode, pv_node, ply }
+    pub fn new(pv_node: Move, ply: i32, context: MoveContext) -> Self {
+        OrderedMovesIter { moves: Vec::new(), phase: MoveOrderingPhase::PvNode, pv_node, excluded: Move::null(), ply, context }
+    }
+
+    // the excluded move is never returned, used by singular extension searches
+    pub fn new_excluding(pv_node: Move, ply: i32, context: MoveContext, excluded: Move) -> Self {
+        OrderedMovesIter { excluded, ..Self::new(pv_node, ply, context) }
     }
 
     fn next_pv_node(&mut self, board: &Board, hist: &HistoryTable, killers: &KillerTable) -> Option<Move> {
         self.phase = MoveOrderingPhase::Generation;
-        if !self.pv_node.is_null() && is_legal(&self.pv_node, board) {
+        if !self.pv_node.is_null() && self.pv_node != self.excluded && is_legal(&self.pv_node, board) {
             Some(self.pv_node)
         } else {
             self.next(board, hist, killers)
@@ -45,10 +53,11 @@ impl OrderedMovesIter {
 
     fn generate_moves(&mut self, board: &Board, hist: &HistoryTable, killers: &KillerTable) -> Option<Move> {
         let mvs = generate_moves(board);
+        let counter = hist.counter_move(&self.context);
         let mut classified_moves: Vec<ClassifiedMove> = Vec::with_capacity(mvs.get_count());
         for mv in mvs.iter() {
-            if *mv != self.pv_node {
-                classified_moves.push(classify_move(*mv, board, hist, killers, self.ply));
+            if *mv != self.pv_node && *mv != self.excluded {
+                classified_moves.push(classify_move(*mv, board, hist, killers, self.ply, &self.context, counter));
             }
         }
         self.moves = classified_moves;
@@ -83,7 +92,8 @@ impl OrderedMovesIter {
             MoveOrderingPhase::Generation => self.generate_moves(board, hist, killers),
             MoveOrderingPhase::Promotions => self.next_by_kind(board, hist, killers, MoveKind::Promotion, MoveOrderingPhase::WinningCaptures),
             MoveOrderingPhase::WinningCaptures => self.next_by_kind(board, hist, killers, MoveKind::WinningCapture, MoveOrderingPhase::KillerMoves),
-            MoveOrderingPhase::KillerMoves => self.next_by_kind(board, hist, killers, MoveKind::KillerMove, MoveOrderingPhase::EqualCaptures),
+            MoveOrderingPhase::KillerMoves => self.next_by_kind(board, hist, killers, MoveKind::KillerMove, MoveOrderingPhase::CounterMove),
+            MoveOrderingPhase::CounterMove => self.next_by_kind(board, hist, killers, MoveKind::CounterMove, MoveOrderingPhase::EqualCaptures),
             MoveOrderingPhase::EqualCaptures => self.next_by_kind(board, hist, killers, MoveKind::EqualCapture, MoveOrderingPhase::Quiet),
             MoveOrderingPhase::Quiet => self.next_by_kind(board, hist,  killers, MoveKind::QuietMove, MoveOrderingPhase::LosingCaptures),
             MoveOrderingPhase::LosingCaptures => self.next_by_kind(board, hist, killers, MoveKind::LosingCapture, MoveOrderingPhase::Exhausted),
@@ -97,7 +107,6 @@ enum QuiesceOrderingPhase {
     Promotions,
     WinningCaptures,
     EqualCaptures,
-    LosingCaptures,
     Exhausted
 }
 
@@ -107,12 +116,15 @@ pub struct QuiesceOrderedMovesIter {
 }
 
 impl QuiesceOrderedMovesIter  {
-    pub fn new(moves: MoveList, board: &Board, history: &HistoryTable, killers: &KillerTable, ply: i32) -> Self {
+    pub fn new(moves: &MoveList, board: &Board, history: &HistoryTable, killers: &KillerTable, ply: i32) -> Self {
         let mut move_kinds: Vec<ClassifiedMove> = Vec::with_capacity(moves.get_count());
+        // captures losing material by see are never searched in quiescence
         for mv in moves.iter() {
             if mv.is_promotion() || mv.is_capture() {
-                let move_kind = classify_move(*mv, board, history, killers, ply);
-                move_kinds.push(move_kind);
+                let move_kind = classify_move(*mv, board, history, killers, ply, &MoveContext::default(), Move::null());
+                if move_kind.kind != MoveKind::LosingCapture {
+                    move_kinds.push(move_kind);
+                }
             }
         }
         QuiesceOrderedMovesIter { moves: move_kinds, phase: QuiesceOrderingPhase::Promotions }
@@ -122,8 +134,7 @@ impl QuiesceOrderedMovesIter  {
         match self.phase {
             QuiesceOrderingPhase::Promotions => self.next_by_kind(MoveKind::Promotion, QuiesceOrderingPhase::WinningCaptures),
             QuiesceOrderingPhase::WinningCaptures => self.next_by_kind(MoveKind::WinningCapture, QuiesceOrderingPhase::EqualCaptures),
-            QuiesceOrderingPhase::EqualCaptures => self.next_by_kind(MoveKind::EqualCapture, QuiesceOrderingPhase::LosingCaptures),
-            QuiesceOrderingPhase::LosingCaptures => self.next_by_kind(MoveKind::LosingCapture, QuiesceOrderingPhase::Exhausted),
+            QuiesceOrderingPhase::EqualCaptures => self.next_by_kind(MoveKind::EqualCapture, QuiesceOrderingPhase::Exhausted),
             QuiesceOrderingPhase::Exhausted => None
         }
     }
@@ -152,27 +163,31 @@ impl QuiesceOrderedMovesIter  {
 }
 
 
-fn classify_move(mv: Move, board: &Board, history: &HistoryTable, killers: &KillerTable, ply: i32) -> ClassifiedMove {
+fn classify_move(mv: Move, board: &Board, history: &HistoryTable, killers: &KillerTable, ply: i32, context: &MoveContext, counter: Move) -> ClassifiedMove {
     if mv.is_promotion() {
         let mut score = MIDGAME_PIECE_VALUES.values[mv.get_promotion_piece()];
         if mv.is_capture() {
-            score += see(board, &mv, board.us);
+            score += see(board, &mv);
         }
         ClassifiedMove::new(MoveKind::Promotion, score, mv)
     }
     else if mv.is_capture() {
-        let score = see(board, &mv, board.us);
-        if score > 0 {
+        let see_score = see(board, &mv);
+        // see decides the bucket, capture history breaks ties inside it
+        let score = see_score + history.capture_score(board, &mv) / CAPTURE_HISTORY_DIVISOR;
+        if see_score > 0 {
            ClassifiedMove::new(MoveKind::WinningCapture, score, mv)
-        } else if score == 0 {
+        } else if see_score == 0 {
             ClassifiedMove::new(MoveKind::EqualCapture, score, mv)
         } else {
             ClassifiedMove::new(MoveKind::LosingCapture , score, mv)
         }
     } else if killers.is_killer(ply, &mv) {
         ClassifiedMove::new(MoveKind::KillerMove, 0, mv)
+    } else if mv == counter {
+        ClassifiedMove::new(MoveKind::CounterMove, 0, mv)
     } else {
-        let hist_score = history.get_val(&mv);
+        let hist_score = history.quiet_score(board, &mv, context);
         ClassifiedMove::new(MoveKind::QuietMove, hist_score, mv)
     }
 }
@@ -184,7 +199,8 @@ enum MoveKind {
     LosingCapture,
     EqualCapture,
     QuietMove,
-    KillerMove
+    KillerMove,
+    CounterMove
 }
 
 struct ClassifiedMove {
@@ -198,124 +214,3 @@ impl ClassifiedMove {
         ClassifiedMove { kind, score, mv }
     }
 }
-
-//king score is intentionally high
-const SEE_PIECE_VALS: [i32; 6] = [100, 300, 300, 500, 900, 20_000];
-
-fn see(board: &Board, cap: &Move, color: usize) -> i32 {
-    let pieces = board.get_pieces(color);
-    let enemy_pieces = board.get_pieces(flip_color(color));
-
-    let target = cap.get_target_field();
-    let start = cap.get_start_field();
-    let mut piece_type = pieces.get_piece_at(start);
-    let victim_type = if cap.is_en_passant() {PAWN} else {enemy_pieces.get_piece_at(target)};
-
-
-    let mut gain = [0; 32];
-    gain[0] = SEE_PIECE_VALS[victim_type];
-    let mut d = 0;
-
-    let mut attackers = [[0u64; 6]; 2];
-    let mut on_attack = flip_color(color);
-    let mut used = cap.get_start_bb();
-    let mut occ = board.get_occupancy() & !used;
-    if cap.is_en_passant() {
-        let captured_pawn_sq = if color == WHITE { target - 8 } else { target + 8 };
-        occ &= !(1u64 << captured_pawn_sq);
-    }
-
-    find_static_attackers(&mut attackers[color], board.get_pieces(color), target, color, used);
-    find_static_attackers(&mut attackers[on_attack], board.get_pieces(on_attack), target, on_attack, used);
-
-    loop {
-        d += 1;
-        gain[d] = SEE_PIECE_VALS[piece_type] - gain[d - 1];
-
-        if -gain[d-1] < 0 && gain[d] < 0 {
-            break;
-        }
-        let lva_res = get_lva(
-            &mut attackers[on_attack], 
-            target,
-            occ, 
-            board.get_pieces(on_attack), used
-        );
-
-        match lva_res {
-            None => break,
-            Some(p_type) => {
-                piece_type = p_type;
-                let bb = isolate_lsb(attackers[on_attack][piece_type]);
-                attackers[on_attack][piece_type] &= !bb;
-                used |= bb;
-                occ &= !bb;
-                on_attack = flip_color(on_attack);
-            }
-        }
-    }
-    while d > 1 {
-        d -= 1;
-        gain[d-1] = -std::cmp::max(-gain[d-1], gain[d]);
-    }
-
-    gain[0]
-}
-
-fn get_lva(attackers: &mut [u64; 6], sq: usize, occ: u64, pieces: &PieceSet, used: u64) -> Option<usize> {
-    if attackers[PAWN] != 0 {
-        return Some(PAWN);
-    } 
-    if attackers[KNIGHT] != 0 {
-        return Some(KNIGHT);
-    } 
-    update_slider_attacks(attackers, sq, occ, pieces, used);
-    if attackers[BISHOP] != 0 {
-        return Some(BISHOP);
-    } 
-    if attackers[ROOK] != 0 {
-        return Some(ROOK);
-    } 
-    if attackers[QUEEN] != 0 {
-        return Some(QUEEN);
-    } 
-    if attackers[KING] != 0 {
-        return Some(KING);
-    } 
-    None
-}
-
-fn update_slider_attacks(attackers: &mut [u64; 6], sq: usize, occ: u64, pieces: &PieceSet, used: u64) {
-    let mg = get_mg();
-    let rook_attacks = mg.get_rook_attacks(sq, occ);
-    let bishop_attacks = mg.get_bishop_attacks(sq, occ);
-
-    let bishop_attackers = pieces.get_bishops() & bishop_attacks & !used;
-    attackers[BISHOP] = bishop_attackers;
-
-    let rook_attackers = pieces.get_rooks() & rook_attacks & !used;
-    attackers[ROOK] = rook_attackers;
-
-    let queen_attackers = pieces.get_queens() & (rook_attacks | bishop_attacks) & !used; 
-    attackers[QUEEN] = queen_attackers;
-}
-
-fn find_static_attackers(attackers: &mut [u64; 6], pieces: &PieceSet, sq: usize, color: usize, used: u64) {
-    let mg = get_mg();
-
-    let pawns = pieces.get_pawns();
-    let pawn_attacks = mg.get_pawn_attacks(sq, flip_color(color));
-    let pawn_attackers = pawns & pawn_attacks & !used;
-    attackers[PAWN] = pawn_attackers;
-
-    let knights = pieces.get_knights();
-    let knight_attacks = mg.get_knight_attacks(sq);
-    let knight_attackers = knights & knight_attacks & !used;
-    attackers[KNIGHT] = knight_attackers;
-
-    let king = pieces.get_king();
-    let king_attacks = mg.get_king_attacks(sq);
-    let king_attackers = king & king_attacks & !used;
-    attackers[KING] = king_attackers;
-}
-
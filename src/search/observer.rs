@@ -0,0 +1,77 @@
+use crate::moving::mv::Move;
+
+use super::alpha_beta::{MATE, MATE_BOUND};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    Cp(i32),
+    // full moves until mate, negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    pub fn from_search(score: i32) -> Self {
+        if score > MATE_BOUND {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score < -MATE_BOUND {
+            Score::Mate(-(MATE + score) / 2)
+        } else {
+            Score::Cp(score)
+        }
+    }
+}
+
+// lower and upper bounds come from aspiration windows that failed high or low
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone)]
+pub struct SearchInfo {
+    pub depth: i32,
+    pub seldepth: i32,
+    // 1 based rank of the line when several are searched
+    pub multipv: usize,
+    pub score: Score,
+    pub bound: Bound,
+    pub nodes: u64,
+    pub nps: u64,
+    // milliseconds since the search started
+    pub time: u128,
+    // permille of the transposition table in use
+    pub hashfull: usize,
+    pub pv: Vec<Move>,
+}
+
+// receives the progress of a search, called from the search threads
+pub trait SearchObserver: Send + Sync {
+    fn on_info(&self, info: &SearchInfo);
+
+    fn on_best_move(&self, best_move: Move, ponder_move: Option<Move>);
+}
+
+pub struct SilentObserver;
+
+impl SearchObserver for SilentObserver {
+    fn on_info(&self, _info: &SearchInfo) {}
+
+    fn on_best_move(&self, _best_move: Move, _ponder_move: Option<Move>) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::Score;
+    use crate::search::alpha_beta::MATE;
+
+    #[test]
+    fn should_convert_mate_scores_to_moves() {
+        assert_eq!(Score::from_search(MATE - 1), Score::Mate(1));
+        assert_eq!(Score::from_search(MATE - 3), Score::Mate(2));
+        assert_eq!(Score::from_search(-MATE + 2), Score::Mate(-1));
+        assert_eq!(Score::from_search(-MATE + 4), Score::Mate(-2));
+        assert_eq!(Score::from_search(35), Score::Cp(35));
+    }
+}
@@ -0,0 +1,99 @@
+// margins are in centipawns per ply of remaining depth, a max depth of 0 switches the technique off
+#[derive(Clone, Copy)]
+pub struct PruningConfig {
+    pub rfp_margin: i32,
+    pub rfp_max_depth: i32,
+    pub futility_margin: i32,
+    pub futility_max_depth: i32,
+    pub razor_margin: i32,
+    pub razor_max_depth: i32,
+    pub lmp_base: i32,
+    pub lmp_max_depth: i32,
+}
+
+impl Default for PruningConfig {
+    fn default() -> Self {
+        PruningConfig {
+            rfp_margin: 80,
+            rfp_max_depth: 6,
+            futility_margin: 120,
+            futility_max_depth: 5,
+            razor_margin: 250,
+            razor_max_depth: 2,
+            lmp_base: 3,
+            lmp_max_depth: 6,
+        }
+    }
+}
+
+impl PruningConfig {
+    // static eval is so far above beta that a shallow search won't bring it back down
+    pub fn reverse_futility(&self, depth_left: i32, static_eval: i32, beta: i32, improving: bool) -> bool {
+        depth_left <= self.rfp_max_depth
+            && static_eval - self.rfp_margin * (depth_left - improving as i32) >= beta
+    }
+
+    // static eval is so far below alpha that only a tactical gain could raise it
+    pub fn razor(&self, depth_left: i32, static_eval: i32, alpha: i32) -> bool {
+        depth_left <= self.razor_max_depth && static_eval + self.razor_margin * depth_left < alpha
+    }
+
+    // quiet moves can't lift a position this far below alpha
+    pub fn futile(&self, depth_left: i32, static_eval: i32, alpha: i32) -> bool {
+        depth_left <= self.futility_max_depth && static_eval + self.futility_margin * depth_left <= alpha
+    }
+
+    // late quiet moves are skipped once this many moves were tried
+    pub fn late_move_count(&self, depth_left: i32, improving: bool) -> i32 {
+        if depth_left > self.lmp_max_depth {
+            i32::MAX
+        } else {
+            (self.lmp_base + depth_left * depth_left) / (2 - improving as i32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PruningConfig;
+
+    #[test]
+    fn should_prune_by_margin_per_ply_up_to_max_depth() {
+        let config = PruningConfig::default();
+        // reverse futility, 80 per ply above beta
+        assert!(config.reverse_futility(3, 240, 0, false));
+        assert!(!config.reverse_futility(3, 239, 0, false));
+        assert!(!config.reverse_futility(7, 10_000, 0, false));
+        // razoring, 250 per ply below alpha
+        assert!(config.razor(2, -501, 0));
+        assert!(!config.razor(2, -500, 0));
+        assert!(!config.razor(3, -10_000, 0));
+        // futility, 120 per ply below alpha
+        assert!(config.futile(4, -480, 0));
+        assert!(!config.futile(4, -479, 0));
+        assert!(!config.futile(6, -10_000, 0));
+        // late move pruning, base plus depth squared
+        assert_eq!(config.late_move_count(3, true), 12);
+        assert_eq!(config.late_move_count(7, true), i32::MAX);
+    }
+
+    #[test]
+    fn should_weigh_in_the_improving_flag() {
+        let config = PruningConfig::default();
+        // an improving position gets cut with a ply less of margin above beta
+        assert!(!config.reverse_futility(3, 200, 0, false));
+        assert!(config.reverse_futility(3, 200, 0, true));
+        // but twice as many quiet moves before late moves are skipped
+        assert_eq!(config.late_move_count(3, false), 6);
+        assert_eq!(config.late_move_count(3, true), 12);
+    }
+
+    #[test]
+    fn should_switch_off_with_zero_max_depth() {
+        let config = PruningConfig { rfp_max_depth: 0, futility_max_depth: 0, razor_max_depth: 0, lmp_max_depth: 0, ..Default::default() };
+        assert!(!config.reverse_futility(1, 10_000, 0, false));
+        assert!(!config.razor(1, -10_000, 0));
+        assert!(!config.futile(1, -10_000, 0));
+        assert_eq!(config.late_move_count(1, false), i32::MAX);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::moving::mv::Move;
+
+use super::alpha_beta::MAX_PLY;
+
+// row ply holds the best line found from ply on. a node empties its row on entry
+// and copies the row below behind every move that raises alpha
+pub struct PvTable {
+    moves: Box<[[Move; MAX_PLY]; MAX_PLY]>,
+    len: [usize; MAX_PLY],
+}
+
+impl Default for PvTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PvTable {
+    pub fn new() -> Self {
+        PvTable { moves: Box::new([[Move::null(); MAX_PLY]; MAX_PLY]), len: [0; MAX_PLY] }
+    }
+
+    pub fn clear(&mut self, ply: usize) {
+        self.len[ply] = ply;
+    }
+
+    pub fn update(&mut self, ply: usize, mv: Move) {
+        let end = self.len[ply + 1].max(ply + 1);
+        let (rows, below) = self.moves.split_at_mut(ply + 1);
+        rows[ply][ply] = mv;
+        rows[ply][ply + 1..end].copy_from_slice(&below[0][ply + 1..end]);
+        self.len[ply] = end;
+    }
+
+    pub fn line(&self, ply: usize) -> &[Move] {
+        &self.moves[ply][ply..self.len[ply]]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PvTable;
+    use crate::moving::mv::Move;
+
+    #[test]
+    fn should_drop_the_old_line_when_a_new_move_raises_alpha() {
+        let mut pv = PvTable::new();
+        let (a, b, c) = (Move::new_quiet(12, 28), Move::new_quiet(52, 36), Move::new_quiet(6, 21));
+        pv.clear(1);
+        pv.clear(2);
+        pv.update(1, b);
+        pv.update(0, a);
+        assert!(pv.line(0) == [a, b]);
+        // a later child that ends in a leaf leaves only its own move
+        pv.clear(1);
+        pv.update(0, c);
+        assert!(pv.line(0) == [c]);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::bitboard_helpers::{flip_color, isolate_lsb};
+use crate::constants::{BISHOP, KING, KNIGHT, NONE, PAWN, QUEEN, ROOK, WHITE};
+use crate::moving::move_generation::get_mg;
+use crate::moving::mv::Move;
+use crate::position::board::Board;
+use crate::position::piece_set::PieceSet;
+
+//king score is intentionally high
+const SEE_PIECE_VALS: [i32; 6] = [100, 300, 300, 500, 900, 20_000];
+
+// material balance of the exchange sequence started by mv on its target square,
+// slider attackers are recomputed after every capture so x-rays are picked up.
+// a quiet move starts with a gain of zero, so a negative result means it hangs the piece
+pub fn see(board: &Board, mv: &Move) -> i32 {
+    see_for(board, mv, board.us)
+}
+
+// value of the piece taken by mv, zero for quiet moves
+pub fn captured_value(board: &Board, mv: &Move) -> i32 {
+    if mv.is_en_passant() {
+        SEE_PIECE_VALS[PAWN]
+    } else if mv.is_capture() {
+        SEE_PIECE_VALS[board.get_enemy_pieces().get_piece_at(mv.get_target_field())]
+    } else {
+        0
+    }
+}
+
+fn see_for(board: &Board, cap: &Move, color: usize) -> i32 {
+    let pieces = board.get_pieces(color);
+    let enemy_pieces = board.get_pieces(flip_color(color));
+
+    let target = cap.get_target_field();
+    let start = cap.get_start_field();
+    let mut piece_type = pieces.get_piece_at(start);
+    let victim_type = if cap.is_en_passant() {PAWN} else {enemy_pieces.get_piece_at(target)};
+
+
+    let mut gain = [0; 32];
+    gain[0] = if victim_type == NONE { 0 } else { SEE_PIECE_VALS[victim_type] };
+    let mut d = 0;
+
+    let mut attackers = [[0u64; 6]; 2];
+    let mut on_attack = flip_color(color);
+    let mut used = cap.get_start_bb();
+    let mut occ = board.get_occupancy() & !used;
+    if cap.is_en_passant() {
+        let captured_pawn_sq = if color == WHITE { target - 8 } else { target + 8 };
+        occ &= !(1u64 << captured_pawn_sq);
+    }
+
+    find_static_attackers(&mut attackers[color], board.get_pieces(color), target, color, used);
+    find_static_attackers(&mut attackers[on_attack], board.get_pieces(on_attack), target, on_attack, used);
+
+    loop {
+        d += 1;
+        gain[d] = SEE_PIECE_VALS[piece_type] - gain[d - 1];
+
+        if -gain[d-1] < 0 && gain[d] < 0 {
+            break;
+        }
+        let lva_res = get_lva(
+            &mut attackers[on_attack], 
+            target,
+            occ, 
+            board.get_pieces(on_attack), used
+        );
+
+        match lva_res {
+            None => break,
+            Some(p_type) => {
+                piece_type = p_type;
+                let bb = isolate_lsb(attackers[on_attack][piece_type]);
+                attackers[on_attack][piece_type] &= !bb;
+                used |= bb;
+                occ &= !bb;
+                on_attack = flip_color(on_attack);
+            }
+        }
+    }
+    while d > 1 {
+        d -= 1;
+        gain[d-1] = -std::cmp::max(-gain[d-1], gain[d]);
+    }
+
+    gain[0]
+}
+
+fn get_lva(attackers: &mut [u64; 6], sq: usize, occ: u64, pieces: &PieceSet, used: u64) -> Option<usize> {
+    if attackers[PAWN] != 0 {
+        return Some(PAWN);
+    } 
+    if attackers[KNIGHT] != 0 {
+        return Some(KNIGHT);
+    } 
+    update_slider_attacks(attackers, sq, occ, pieces, used);
+    if attackers[BISHOP] != 0 {
+        return Some(BISHOP);
+    } 
+    if attackers[ROOK] != 0 {
+        return Some(ROOK);
+    } 
+    if attackers[QUEEN] != 0 {
+        return Some(QUEEN);
+    } 
+    if attackers[KING] != 0 {
+        return Some(KING);
+    } 
+    None
+}
+
+fn update_slider_attacks(attackers: &mut [u64; 6], sq: usize, occ: u64, pieces: &PieceSet, used: u64) {
+    let mg = get_mg();
+    let rook_attacks = mg.get_rook_attacks(sq, occ);
+    let bishop_attacks = mg.get_bishop_attacks(sq, occ);
+
+    let bishop_attackers = pieces.get_bishops() & bishop_attacks & !used;
+    attackers[BISHOP] = bishop_attackers;
+
+    let rook_attackers = pieces.get_rooks() & rook_attacks & !used;
+    attackers[ROOK] = rook_attackers;
+
+    let queen_attackers = pieces.get_queens() & (rook_attacks | bishop_attacks) & !used; 
+    attackers[QUEEN] = queen_attackers;
+}
+
+fn find_static_attackers(attackers: &mut [u64; 6], pieces: &PieceSet, sq: usize, color: usize, used: u64) {
+    let mg = get_mg();
+
+    let pawns = pieces.get_pawns();
+    let pawn_attacks = mg.get_pawn_attacks(sq, color);
+    let pawn_attackers = pawns & pawn_attacks & !used;
+    attackers[PAWN] = pawn_attackers;
+
+    let knights = pieces.get_knights();
+    let knight_attacks = mg.get_knight_attacks(sq);
+    let knight_attackers = knights & knight_attacks & !used;
+    attackers[KNIGHT] = knight_attackers;
+
+    let king = pieces.get_king();
+    let king_attacks = mg.get_king_attacks(sq);
+    let king_attackers = king & king_attacks & !used;
+    attackers[KING] = king_attackers;
+}
+
+#[cfg(test)]
+mod test {
+    use super::see;
+    use crate::fen_parsing::parse_fen::parse_fen;
+    use crate::moving::move_generation::generate_moves;
+
+    fn see_of(fen: &str, mv_str: &str) -> i32 {
+        let board = parse_fen(fen).unwrap();
+        let moves = generate_moves(&board);
+        let mv = moves.iter().find(|m| m.to_str() == mv_str).unwrap();
+        see(&board, mv)
+    }
+
+    #[test]
+    fn should_see_queen_takes_pawn_defended_by_pawn_as_losing() {
+        let score = see_of("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1", "d2d5");
+        assert_eq!(score, 100 - 900);
+    }
+
+    #[test]
+    fn should_see_undefended_capture_as_winning() {
+        let score = see_of("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1", "d2d5");
+        assert_eq!(score, 500);
+    }
+
+    #[test]
+    fn should_count_xray_attacker_behind_slider() {
+        // the queen on d1 backs up the rook through d2, so the exchange on d5 nets a pawn
+        let score = see_of("4k3/3r4/8/3p4/8/8/3R4/3QK3 w - - 0 1", "d2d5");
+        assert_eq!(score, 100);
+    }
+
+    #[test]
+    fn should_see_quiet_move_to_attacked_square_as_hanging() {
+        assert_eq!(see_of("4k3/8/2p5/8/8/8/3Q4/4K3 w - - 0 1", "d2d5"), -900);
+        assert_eq!(see_of("4k3/8/8/8/8/8/3Q4/4K3 w - - 0 1", "d2d5"), 0);
+    }
+}
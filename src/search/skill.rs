@@ -0,0 +1,126 @@
+use rand::Rng;
+
+use crate::moving::mv::Move;
+
+pub const MAX_SKILL_LEVEL: i32 = 20;
+const SKILL_MULTI_PV: usize = 4;
+const NODES_AT_LEVEL_ZERO: f64 = 400.0;
+const NODES_GROWTH_PER_LEVEL: f64 = 1.35;
+// largest random bonus a candidate can get, shrinking by this much per level
+const ERROR_PER_LEVEL: f64 = 15.0;
+
+// elo of every skill level below full strength. the gaps were measured with
+// `skill_match <level> <level - 1> <rounds>`, each level against the one below, and add up from
+// level 0, which is pinned to an assumed 800 since nothing ties the ladder to rated play.
+// results of the calibration, score of the higher level and the gap it implies:
+//   1-0   0.562   43  640 games      11-10  0.602   72  256 games
+//   2-1   0.552   37  640 games      12-11  0.588   62  256 games
+//   3-2   0.620   85  640 games      13-12  0.645  103  256 games
+//   4-3   0.685  135  640 games      14-13  0.609   77  256 games
+//   5-4   0.611   78  640 games      15-14  0.605   74  128 games
+//   6-5   0.651  108  640 games      16-15  0.645  103  128 games
+//   7-6   0.656  112  640 games      17-16  0.676  128  128 games
+//   8-7   0.634   96  640 games      18-17  0.672  124  128 games
+//   9-8   0.630   92  640 games      19-18  0.680  131  128 games
+//  10-9   0.676  128  256 games
+// retune after changing the search or the skill constants below
+const ELO_LADDER: [i32; MAX_SKILL_LEVEL as usize] = [
+    800, 843, 880, 965, 1100, 1178, 1286, 1398, 1494, 1586,
+    1714, 1786, 1848, 1951, 2028, 2102, 2205, 2333, 2457, 2588,
+];
+pub const MIN_ELO: i32 = ELO_LADDER[0];
+pub const MAX_ELO: i32 = ELO_LADDER[MAX_SKILL_LEVEL as usize - 1];
+
+// the uci strength options, Skill Level is ignored while UCI_LimitStrength is on
+#[derive(Clone, Copy)]
+pub struct StrengthConfig {
+    pub skill_level: i32,
+    pub limit_strength: bool,
+    pub elo: i32,
+}
+
+impl Default for StrengthConfig {
+    fn default() -> Self {
+        StrengthConfig { skill_level: MAX_SKILL_LEVEL, limit_strength: false, elo: MAX_ELO }
+    }
+}
+
+impl StrengthConfig {
+    // None at full strength
+    pub fn skill(&self) -> Option<Skill> {
+        if self.limit_strength {
+            Some(Skill::from_elo(self.elo))
+        } else if self.skill_level < MAX_SKILL_LEVEL {
+            Some(Skill::new(self.skill_level as f64))
+        } else {
+            None
+        }
+    }
+}
+
+// levels are fractional so that every elo between two ladder steps plays differently
+#[derive(Clone, Copy)]
+pub struct Skill {
+    level: f64,
+}
+
+impl Skill {
+    pub fn new(level: f64) -> Self {
+        Skill { level: level.clamp(0.0, MAX_SKILL_LEVEL as f64 - 1.0) }
+    }
+
+    pub fn from_elo(elo: i32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let upper = ELO_LADDER.iter().position(|e| *e >= elo).unwrap_or(0).max(1);
+        let (low, high) = (ELO_LADDER[upper - 1], ELO_LADDER[upper]);
+        Self::new(upper as f64 - 1.0 + (elo - low) as f64 / (high - low) as f64)
+    }
+
+    pub fn max_depth(&self) -> i32 {
+        1 + self.level as i32
+    }
+
+    pub fn max_nodes(&self) -> u64 {
+        (NODES_AT_LEVEL_ZERO * NODES_GROWTH_PER_LEVEL.powf(self.level)) as u64
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        SKILL_MULTI_PV
+    }
+
+    // every candidate gets a random bonus, mates still win since their scores dwarf it
+    pub fn pick_move(&self, lines: &[(i32, Move)], rng: &mut impl Rng) -> Option<Move> {
+        let error = ((MAX_SKILL_LEVEL as f64 - self.level) * ERROR_PER_LEVEL) as i32;
+        lines
+            .iter()
+            .max_by_key(|(score, _)| score + rng.random_range(0..=error))
+            .map(|(_, mv)| *mv)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{Skill, ELO_LADDER, MAX_ELO, MIN_ELO};
+    use crate::moving::mv::Move;
+
+    #[test]
+    fn should_map_elo_between_ladder_steps() {
+        assert_eq!(Skill::from_elo(MIN_ELO).level, 0.0);
+        assert_eq!(Skill::from_elo(MAX_ELO).level, 19.0);
+        let between = Skill::from_elo((ELO_LADDER[4] + ELO_LADDER[5]) / 2).level;
+        assert!(between > 4.0 && between < 5.0);
+    }
+
+    #[test]
+    fn should_keep_mating_move_at_lowest_level() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mate = Move::new_quiet(12, 28);
+        let lines = [(999_995, mate), (40, Move::new_quiet(6, 21)), (35, Move::new_quiet(1, 18))];
+        for _ in 0..100 {
+            assert!(Skill::new(0.0).pick_move(&lines, &mut rng) == Some(mate));
+        }
+    }
+}
@@ -1,9 +1,21 @@
+use std::collections::TryReserveError;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+
 use crate::moving::mv::Move;
 
-const K: usize = 23;
+pub const DEFAULT_HASH_MB: usize = 64;
+pub const MAX_HASH_MB: usize = 65_536;
+const BUCKET_SIZE: usize = 4;
 const GEN_DIFF: i32 = 5;
+const GEN_MASK: i32 = 0x3F;
+// generations an entry has to be older to lose one ply of worth when choosing a victim
+const AGE_WEIGHT: i32 = 2;
+const HASHFULL_SAMPLE: usize = 1000;
+// the low 16 bits of the key word hold the static eval, the rest verifies the key
+const KEY_MASK: u64 = (1 << 48) - 1;
+const NO_EVAL: i16 = i16::MIN;
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, PartialEq)]
 pub enum TTEntryType {
     #[default]
     Exact,
@@ -11,8 +23,6 @@ pub enum TTEntryType {
     Upper
 }
 
-// this has space left, so option does not increase its size
-// if it were to change check for option size
 #[derive(Clone, Copy, Default)]
 pub struct Entry {
     pub key: u64,
@@ -20,38 +30,276 @@ pub struct Entry {
     pub score: i32,
     pub generation: i32,
     pub entry_type: TTEntryType,
-    pub best_move: Move
+    pub best_move: Move,
+    pub static_eval: Option<i32>,
 }
 
+// data layout: move (16) | score (32) | depth (8) | type (2) | generation (6)
+// types are stored as 1 to 3, so a type of 0 marks an empty slot whatever the other fields hold
+// key word layout: static eval (16) | low 48 bits of the key
+impl Entry {
+    fn pack(&self) -> u64 {
+        let entry_type = match self.entry_type {
+            TTEntryType::Exact => 1u64,
+            TTEntryType::Lower => 2,
+            TTEntryType::Upper => 3,
+        };
+        (self.best_move.get_value() as u64)
+            | ((self.score as u32 as u64) << 16)
+            | (((self.depth_left.clamp(0, 255)) as u64) << 48)
+            | (entry_type << 56)
+            | (((self.generation & GEN_MASK) as u64) << 58)
+    }
+
+    fn pack_key(&self) -> u64 {
+        let eval = match self.static_eval {
+            Some(eval) => eval.clamp(NO_EVAL as i32 + 1, i16::MAX as i32) as i16,
+            None => NO_EVAL,
+        };
+        (self.key << 16) | eval as u16 as u64
+    }
+
+    fn unpack(key_word: u64, data: u64) -> Self {
+        let entry_type = match (data >> 56) & 0b11 {
+            1 => TTEntryType::Exact,
+            2 => TTEntryType::Lower,
+            _ => TTEntryType::Upper,
+        };
+        let eval = key_word as u16 as i16;
+        Entry {
+            key: key_word >> 16,
+            static_eval: if eval == NO_EVAL { None } else { Some(eval as i32) },
+            best_move: Move::new_from_val(data as u16),
+            score: (data >> 16) as u32 as i32,
+            depth_left: ((data >> 48) & 0xFF) as i32,
+            entry_type,
+            generation: (data >> 58) as i32,
+        }
+    }
+}
+
+// key is stored xored with data, so a torn write from another thread fails verification
+#[derive(Default)]
+struct AtomicEntry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicEntry {
+    fn load(&self) -> Option<Entry> {
+        let data = self.data.load(Ordering::Relaxed);
+        if !is_occupied(data) {
+            return None;
+        }
+        Some(Entry::unpack(self.key.load(Ordering::Relaxed) ^ data, data))
+    }
+
+    fn write(&self, entry: &Entry) {
+        let data = entry.pack();
+        self.key.store(entry.pack_key() ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+fn is_occupied(data: u64) -> bool {
+    (data >> 56) & 0b11 != 0
+}
+
+// one bucket fills a cache line, so a probe touches memory only once
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    entries: [AtomicEntry; BUCKET_SIZE],
+}
 
 pub struct TTable {
-    mask: usize,
-    table: Box<[Option<Entry>]>,
+    buckets: Box<[Bucket]>,
+    // advanced once per search, so every thread stores with the same generation
+    generation: AtomicI32,
+}
+
+impl Default for TTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
 }
 
-//depth preferred for now
+// shared between search threads, each position maps to a bucket of four entries
 impl TTable {
-    pub fn new() -> Self {
-        let entries = 1 << K;
-        TTable {
-            mask: entries - 1,
-            table: vec![None; entries].into_boxed_slice(),
-        }
+    pub fn new(size_mb: usize) -> Self {
+        Self::try_new(size_mb).expect("transposition table allocation failed")
+    }
+
+    // fails instead of aborting when the memory is not there, e.g. for a huge Hash option
+    pub fn try_new(size_mb: usize) -> Result<Self, TryReserveError> {
+        let count = (size_mb * 1024 * 1024 / size_of::<Bucket>()).max(1);
+        let mut buckets = Vec::new();
+        buckets.try_reserve_exact(count)?;
+        buckets.resize_with(count, Bucket::default);
+        Ok(TTable { buckets: buckets.into_boxed_slice(), generation: AtomicI32::new(0) })
+    }
+
+    // the high bits of key * len spread keys over any table size, not only powers of two
+    fn bucket(&self, key: u64) -> &Bucket {
+        let index = ((key as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[index]
     }
 
     pub fn probe(&self, key: u64) -> Option<Entry> {
-        let index = (key as usize) & self.mask;
-        match self.table[index] {
-            Some(e) if e.key == key => Some(e),
-            _ => None
+        self.bucket(key)
+            .entries
+            .iter()
+            .filter_map(AtomicEntry::load)
+            .find(|e| e.key == key & KEY_MASK)
+            .map(|e| Entry { key, ..e })
+    }
+
+    // an entry for the same position is refreshed unless it is deeper and still young,
+    // otherwise the shallowest and oldest entry of the bucket makes room
+    pub fn store(&self, new: Entry) {
+        let bucket = self.bucket(new.key);
+        let mut victim = &bucket.entries[0];
+        let mut victim_worth = i32::MAX;
+        for slot in bucket.entries.iter() {
+            let Some(existing) = slot.load() else {
+                slot.write(&new);
+                return;
+            };
+            let age = (new.generation - existing.generation) & GEN_MASK;
+            if existing.key == new.key & KEY_MASK {
+                if existing.depth_left > new.depth_left && age < GEN_DIFF {
+                    return;
+                }
+                let best_move = if new.best_move.is_null() { existing.best_move } else { new.best_move };
+                slot.write(&Entry { best_move, ..new });
+                return;
+            }
+            let worth = existing.depth_left - AGE_WEIGHT * age;
+            if worth < victim_worth {
+                victim = slot;
+                victim_worth = worth;
+            }
         }
+        victim.write(&new);
+    }
+
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn generation(&self) -> i32 {
+        self.generation.load(Ordering::Relaxed)
     }
 
-    pub fn store(&mut self, new: Entry) {
-        let index = (new.key as usize) & self.mask;
-        let existing = &self.table[index];
-        if existing.is_none() || existing.is_some_and(|e| e.depth_left <= new.depth_left || new.generation - e.generation >= GEN_DIFF) {
-            self.table[index] = Some(new);
+    pub fn clear(&self) {
+        for entry in self.buckets.iter().flat_map(|b| b.entries.iter()) {
+            entry.clear();
         }
     }
+
+    // permille of entries written by the current search, estimated from the start of the table
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation() & GEN_MASK;
+        let sampled = self.buckets.len().min(HASHFULL_SAMPLE / BUCKET_SIZE);
+        let used = self.buckets[..sampled]
+            .iter()
+            .flat_map(|b| b.entries.iter())
+            .filter_map(AtomicEntry::load)
+            .filter(|e| e.generation == generation)
+            .count();
+        used * 1000 / (sampled * BUCKET_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Entry, TTEntryType, TTable};
+    use crate::moving::mv::Move;
+
+    #[test]
+    fn should_pack_and_unpack_entry() {
+        let entry = Entry {
+            key: 0xDEAD_BEEF_1234_5678,
+            depth_left: 12,
+            score: -999_990,
+            generation: 17,
+            entry_type: TTEntryType::Upper,
+            best_move: Move::new_capture(12, 28),
+            static_eval: Some(-135),
+        };
+        let tt = TTable::new(1);
+        tt.store(entry);
+        let probed = tt.probe(entry.key).unwrap();
+        assert_eq!(probed.depth_left, 12);
+        assert_eq!(probed.score, -999_990);
+        assert_eq!(probed.generation, 17);
+        assert!(probed.entry_type == TTEntryType::Upper);
+        assert!(probed.best_move == entry.best_move);
+        assert_eq!(probed.static_eval, Some(-135));
+        assert_eq!(probed.key, entry.key);
+    }
+
+    #[test]
+    fn should_not_return_entry_for_different_key() {
+        let tt = TTable::new(1);
+        let entry = Entry { key: 1 << 40, depth_left: 3, best_move: Move::new_quiet(1, 18), ..Default::default() };
+        tt.store(entry);
+        assert!(tt.probe(1 << 41).is_none());
+    }
+
+    #[test]
+    fn should_keep_all_entries_of_a_bucket() {
+        // a single bucket, every key lands in it
+        let tt = TTable::new(0);
+        for key in 1..=4u64 {
+            tt.store(Entry { key, depth_left: 3, best_move: Move::new_quiet(1, 18), ..Default::default() });
+        }
+        assert!((1..=4u64).all(|key| tt.probe(key).is_some()));
+        assert_eq!(tt.hashfull(), 1000);
+    }
+
+    #[test]
+    fn should_keep_entry_whose_fields_are_all_zero() {
+        let tt = TTable::new(0);
+        tt.store(Entry { key: 7, ..Default::default() });
+        let probed = tt.probe(7).unwrap();
+        assert!(probed.entry_type == TTEntryType::Exact && probed.best_move.is_null());
+        assert_eq!((probed.score, probed.depth_left, probed.generation), (0, 0, 0));
+    }
+
+    #[test]
+    fn should_replace_shallowest_entry_when_bucket_is_full() {
+        let tt = TTable::new(0);
+        for key in 1..=4u64 {
+            tt.store(Entry { key, depth_left: key as i32 + 4, best_move: Move::new_quiet(1, 18), ..Default::default() });
+        }
+        tt.store(Entry { key: 5, depth_left: 1, best_move: Move::new_quiet(1, 18), ..Default::default() });
+        assert!(tt.probe(1).is_none());
+        assert!(tt.probe(5).is_some());
+        tt.clear();
+        assert!(tt.probe(5).is_none());
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn should_count_only_current_generation_as_full() {
+        let tt = TTable::new(0);
+        for key in 1..=4u64 {
+            tt.store(Entry { key, generation: key as i32 % 2, best_move: Move::new_quiet(1, 18), ..Default::default() });
+        }
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 500);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn should_fail_to_allocate_impossible_table() {
+        assert!(TTable::try_new(usize::MAX / (1024 * 1024)).is_err());
+    }
 }
@@ -3,4 +3,7 @@ pub mod nps;
 pub mod test_suites;
 pub mod transpositions;
 pub mod itflat;
+pub mod skill_match;
 mod epd;
+#[cfg(test)]
+pub mod common;
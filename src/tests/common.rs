@@ -0,0 +1,26 @@
+use std::sync::Mutex;
+
+use crate::moving::mv::Move;
+use crate::search::observer::{SearchInfo, SearchObserver};
+
+// a quiet middlegame with plenty of sensible moves, searched by several modules' tests
+pub const ITALIAN_GAME: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+
+// keeps everything a search reports so the test can look at it afterwards
+#[derive(Default)]
+pub struct Recorder {
+    pub infos: Mutex<Vec<SearchInfo>>,
+    pub best_move: Mutex<Option<Move>>,
+    pub best_moves: Mutex<usize>,
+}
+
+impl SearchObserver for Recorder {
+    fn on_info(&self, info: &SearchInfo) {
+        self.infos.lock().unwrap().push(info.clone());
+    }
+
+    fn on_best_move(&self, best_move: Move, _ponder_move: Option<Move>) {
+        *self.best_move.lock().unwrap() = Some(best_move);
+        *self.best_moves.lock().unwrap() += 1;
+    }
+}
@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use crate::{fen_parsing::parse_fen::parse_fen, search::alpha_beta::Searcher};
+use crate::{fen_parsing::parse_fen::parse_fen, search::alpha_beta::Searcher, uci::message::{write_line, Output}};
 
 const POSITIONS: [&str; 5] = [
     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
@@ -12,14 +12,14 @@ const POSITIONS: [&str; 5] = [
 
 const DEPTHS: [i32; 5] = [9, 7, 8, 8, 8];
 
-pub fn make_comp_tests() {
+pub fn make_comp_tests(output: &Output) {
     let mut flat_sum = 0f32;
     let mut iter_sum = 0f32;
     let mut cnt = 0;
 
     for (position, depth) in POSITIONS.iter().zip(DEPTHS.iter()) {
         cnt += 1;
-        println!("Position {cnt}");
+        write_line(output, format!("Position {cnt}"));
         let mut board = parse_fen(position).unwrap();
         
         let mut searcher = Searcher::new();
@@ -27,7 +27,7 @@ pub fn make_comp_tests() {
         searcher.search_flat(&mut board, *depth);
         let flat_time = start.elapsed().as_secs_f32();
         let node_cnt = searcher.get_nodes_searched();
-        println!("Fixed depth time: {flat_time} node count: {node_cnt}");
+        write_line(output, format!("Fixed depth time: {flat_time} node count: {node_cnt}"));
 
 
         let mut searcher = Searcher::new();
@@ -35,7 +35,7 @@ pub fn make_comp_tests() {
         searcher.search_to_depth(&mut board, *depth);
         let iter_time = start.elapsed().as_secs_f32();
         let node_cnt = searcher.get_nodes_searched();
-        println!("Iterative deepening time: {iter_time} node count: {node_cnt}\n");
+        write_line(output, format!("Iterative deepening time: {iter_time} node count: {node_cnt}\n"));
 
         flat_sum += flat_time;
         iter_sum += iter_time;
@@ -44,6 +44,6 @@ pub fn make_comp_tests() {
     let iter_avg = iter_sum / 5.;
     let flat_avg = flat_sum / 5.;
 
-    println!("Fixed depth time  sum: {flat_sum}  avg: {flat_avg}");
-    println!("Iterative deepening time   sum: {iter_sum}  avg: {iter_avg}");
+    write_line(output, format!("Fixed depth time  sum: {flat_sum}  avg: {flat_avg}"));
+    write_line(output, format!("Iterative deepening time   sum: {iter_sum}  avg: {iter_avg}"));
 }
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::constants::WHITE;
+use crate::fen_parsing::parse_fen::parse_fen;
+use crate::moving::move_generation::generate_moves;
+use crate::position::board::Board;
+use crate::search::alpha_beta::Searcher;
+use crate::search::skill::Skill;
+use crate::search::transposition::TTable;
+
+const OPENINGS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+    "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1",
+    "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkb1r/pppppppp/5n2/8/2P5/8/PP1PPPPP/RNBQKBNR w KQkq - 1 2",
+    "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pppp1ppp/4p3/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pp1ppppp/2p5/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+];
+const MAX_GAME_PLIES: usize = 300;
+const MATCH_HASH_MB: usize = 16;
+
+// plays level_a against level_b with both colours from every opening,
+// returns the score of level_a and the elo difference it implies
+pub fn skill_match(level_a: f64, level_b: f64, rounds: usize) -> (f64, f64) {
+    let mut points = 0.0;
+    let mut games = 0;
+    for _ in 0..rounds {
+        for opening in OPENINGS {
+            for a_is_white in [true, false] {
+                let board = parse_fen(opening).unwrap();
+                let (white, black) = if a_is_white { (level_a, level_b) } else { (level_b, level_a) };
+                let white_result = play_game(board, Skill::new(white), Skill::new(black));
+                points += if a_is_white { white_result } else { 1.0 - white_result };
+                games += 1;
+            }
+        }
+    }
+    let score = points / games as f64;
+    let clamped = score.clamp(0.001, 0.999);
+    (score, -400.0 * (1.0 / clamped - 1.0).log10())
+}
+
+// 1 for a white win, 0.5 for a draw, 0 for a black win
+fn play_game(mut board: Board, white: Skill, black: Skill) -> f64 {
+    let mut players = [new_player(), new_player()];
+    let mut rng = rand::rng();
+    for _ in 0..MAX_GAME_PLIES {
+        if generate_moves(&board).get_count() == 0 {
+            return if !board.is_check() { 0.5 } else if board.us == WHITE { 0.0 } else { 1.0 };
+        }
+        if board.is_draw(0) {
+            return 0.5;
+        }
+        let skill = if board.us == WHITE { white } else { black };
+        let searcher = &mut players[board.us];
+        let stop = Arc::new(AtomicBool::new(false));
+        searcher.set_multi_pv(skill.multi_pv());
+        searcher.set_node_limit(Some(skill.max_nodes()));
+        searcher.prepare_search(stop.clone());
+        for depth in 1..=skill.max_depth() {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            searcher.make_search(&mut board, depth);
+        }
+        let mv = skill.pick_move(searcher.get_root_lines(), &mut rng).unwrap();
+        board.make_move(&mv);
+    }
+    0.5
+}
+
+fn new_player() -> Searcher {
+    Searcher::new_shared(Arc::new(TTable::new(MATCH_HASH_MB)), false)
+}
@@ -1,6 +1,6 @@
 use regex::Regex;
 
-use crate::{fen_parsing::parse_fen::parse_fen, search::alpha_beta::Searcher};
+use crate::{fen_parsing::parse_fen::parse_fen, search::{alpha_beta::Searcher, extensions::ExtensionConfig}, uci::message::{write_line, Output}};
 #[derive(Clone)]
 struct WacEntry {
     pub fen: String,
@@ -9,19 +9,20 @@ struct WacEntry {
 }
 
 const INIT_TIME: u64 = 1000;
-pub fn wac_test() {
+pub fn wac_test(extensions: ExtensionConfig, output: &Output) {
     let mut entries = parse_wac();
     let mut time = INIT_TIME;
     let mut passed_count = 0;
     let mut depth_arr = [0; 300];
     while !entries.is_empty() {
-        println!("Time per move: {}s", time / 1000);
+        write_line(output, format!("Time per move: {}s", time / 1000));
         let mut failed_entries: Vec<WacEntry> = Vec::new();
         let mut failed_count = 0;
         let mut failed_ids: Vec<String> = Vec::new();
         for (i, entry) in entries.clone().iter().enumerate() {
             let mut board = parse_fen(&entry.fen).unwrap();
             let mut searcher = Searcher::new();
+            searcher.set_extensions(extensions);
             let search_result = searcher.search_to_time(&mut board, time, false);
             let mv = search_result.mv;
             let mv_str = mv.to_str();
@@ -29,21 +30,21 @@ pub fn wac_test() {
             if passed {
                 passed_count += 1;
                 depth_arr[i] = search_result.depth_reached;
-                println!("Passed {}", entry.id);
+                write_line(output, format!("Passed {}", entry.id));
             } else {
                 failed_count += 1;
                 failed_ids.push(entry.id.clone());
                 failed_entries.push(entry.clone());
-                println!("Failed {}, move found: {}, accepted moves: {}", 
-                    entry.id, mv_str, entry.best_moves.join(" "));
+                write_line(output, format!("Failed {}, move found: {}, accepted moves: {}",
+                    entry.id, mv_str, entry.best_moves.join(" ")));
             }
         }
         let depth_sum: i32 = depth_arr.iter().sum();
         let depth_avg = depth_sum as f64 / 300.;
-        println!("WAC test finished, passed: {}, failed: {}, ratio: {}, avg depth: {:.2}", 
-            passed_count, failed_count, ((passed_count as f32 / 300.) * 100.) as i32, depth_avg);
+        write_line(output, format!("WAC test finished, passed: {}, failed: {}, ratio: {}, avg depth: {:.2}",
+            passed_count, failed_count, ((passed_count as f32 / 300.) * 100.) as i32, depth_avg));
         if failed_count > 0 {
-            println!("Failed positions: {}", failed_ids.join(" "));
+            write_line(output, format!("Failed positions: {}", failed_ids.join(" ")));
         }
         entries = failed_entries;
         time *= 2;
@@ -1,3 +1,9 @@
+pub mod command;
 pub mod engine;
+pub mod message;
+pub mod options;
 pub mod perft;
+pub mod search_limits;
+pub mod time_manager;
+pub mod uci_observer;
 pub mod uci_interpreter;
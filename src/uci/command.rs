@@ -0,0 +1,298 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{FromStr, SplitWhitespace};
+
+use crate::fen_parsing::parse_fen::FenError;
+use crate::uci::search_limits::{Clock, SearchLimits};
+
+pub const START_POS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// a gui to engine line after parsing, moves are only checked for their shape here,
+// whether they are legal depends on the position they get played in
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: String },
+    UciNewGame,
+    Position { fen: String, moves: Vec<String> },
+    Go(GoCommand),
+    Stop,
+    PonderHit,
+    Quit,
+    // everything below is ours, not part of uci
+    Help,
+    Perft(i32),
+    Wac,
+    Nps,
+    TtTest(i32),
+    IterTest,
+    // used to calibrate the elo ladder
+    SkillMatch { a: f64, b: f64, rounds: usize },
+}
+
+#[derive(Default)]
+pub struct GoCommand {
+    pub limits: SearchLimits,
+    pub search_moves: Vec<String>,
+    pub ponder: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UciError {
+    UnknownCommand(String),
+    UnexpectedToken { command: &'static str, token: String },
+    MissingValue(String),
+    InvalidValue { name: String, value: String, expected: &'static str },
+    InvalidMove(String),
+    InvalidFen(FenError),
+    IllegalMove(String),
+    InvalidOption(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciError::UnknownCommand(command) => write!(f, "Unknown command: '{command}'. Type help for more information."),
+            UciError::UnexpectedToken { command, token } => write!(f, "Unexpected '{token}' in {command}"),
+            UciError::MissingValue(name) => write!(f, "Missing value for '{name}'"),
+            UciError::InvalidValue { name, value, expected } => write!(f, "Invalid value for '{name}': '{value}', expected {expected}"),
+            UciError::InvalidMove(mv) => write!(f, "Invalid move: '{mv}'"),
+            UciError::InvalidFen(e) => write!(f, "Invalid fen: {e:?}"),
+            UciError::IllegalMove(mv) => write!(f, "Illegal move: '{mv}'"),
+            UciError::InvalidOption(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+type Tokens<'a> = Peekable<SplitWhitespace<'a>>;
+
+impl FromStr for UciCommand {
+    type Err = UciError;
+
+    fn from_str(line: &str) -> Result<Self, UciError> {
+        let mut tokens = line.split_whitespace().peekable();
+        let Some(command) = tokens.next() else {
+            return Err(UciError::UnknownCommand(String::new()));
+        };
+        let parsed = match command {
+            "uci" => UciCommand::Uci,
+            "debug" => match tokens.next() {
+                Some("on") => UciCommand::Debug(true),
+                Some("off") => UciCommand::Debug(false),
+                Some(t) => return Err(invalid("debug", t, "on or off")),
+                None => return Err(missing("debug")),
+            },
+            "isready" => UciCommand::IsReady,
+            "setoption" => parse_setoption(&mut tokens)?,
+            "ucinewgame" => UciCommand::UciNewGame,
+            "position" => parse_position(&mut tokens)?,
+            "go" => parse_go(&mut tokens)?,
+            "stop" => UciCommand::Stop,
+            "ponderhit" => UciCommand::PonderHit,
+            "quit" => UciCommand::Quit,
+            "help" => UciCommand::Help,
+            "wac" => UciCommand::Wac,
+            "nps" => UciCommand::Nps,
+            "tt_test" => UciCommand::TtTest(parse_value("tt_test", tokens.next())?),
+            "iter_test" => UciCommand::IterTest,
+            "skill_match" => UciCommand::SkillMatch {
+                a: parse_value("level a", tokens.next())?,
+                b: parse_value("level b", tokens.next())?,
+                rounds: parse_positive("rounds", tokens.next())?,
+            },
+            _ => return Err(UciError::UnknownCommand(command.to_owned())),
+        };
+        // go, position and setoption take the whole line, the rest take no arguments
+        match tokens.next() {
+            Some(token) => Err(UciError::UnexpectedToken { command: command_name(&parsed), token: token.to_owned() }),
+            None => Ok(parsed),
+        }
+    }
+}
+
+fn command_name(command: &UciCommand) -> &'static str {
+    match command {
+        UciCommand::Uci => "uci",
+        UciCommand::Debug(_) => "debug",
+        UciCommand::IsReady => "isready",
+        UciCommand::SetOption { .. } => "setoption",
+        UciCommand::UciNewGame => "ucinewgame",
+        UciCommand::Position { .. } => "position",
+        UciCommand::Go(_) | UciCommand::Perft(_) => "go",
+        UciCommand::Stop => "stop",
+        UciCommand::PonderHit => "ponderhit",
+        UciCommand::Quit => "quit",
+        UciCommand::Help => "help",
+        UciCommand::Wac => "wac",
+        UciCommand::Nps => "nps",
+        UciCommand::TtTest(_) => "tt_test",
+        UciCommand::IterTest => "iter_test",
+        UciCommand::SkillMatch { .. } => "skill_match",
+    }
+}
+
+// setoption name <id> [value <x>], both may contain spaces
+fn parse_setoption(tokens: &mut Tokens) -> Result<UciCommand, UciError> {
+    match tokens.next() {
+        Some("name") => {}
+        Some(t) => return Err(UciError::UnexpectedToken { command: "setoption", token: t.to_owned() }),
+        None => return Err(missing("name")),
+    }
+    let name: Vec<&str> = tokens.by_ref().take_while(|t| *t != "value").collect();
+    let value: Vec<&str> = tokens.by_ref().collect();
+    if name.is_empty() {
+        return Err(missing("name"));
+    }
+    Ok(UciCommand::SetOption { name: name.join(" "), value: value.join(" ") })
+}
+
+// position [startpos | fen <fen>] [moves <moves>]
+fn parse_position(tokens: &mut Tokens) -> Result<UciCommand, UciError> {
+    let fen = match tokens.next() {
+        Some("startpos") => START_POS.to_owned(),
+        Some("fen") => {
+            let mut fields = Vec::new();
+            while let Some(field) = tokens.next_if(|t| *t != "moves") {
+                fields.push(field);
+            }
+            if fields.is_empty() {
+                return Err(missing("fen"));
+            }
+            fields.join(" ")
+        }
+        Some(t) => return Err(invalid("position", t, "startpos or fen")),
+        None => return Err(missing("position")),
+    };
+    let mut moves = Vec::new();
+    match tokens.next() {
+        Some("moves") => {
+            for mv in tokens.by_ref() {
+                moves.push(parse_move(mv)?);
+            }
+        }
+        Some(t) => return Err(UciError::UnexpectedToken { command: "position", token: t.to_owned() }),
+        None => {}
+    }
+    Ok(UciCommand::Position { fen, moves })
+}
+
+const GO_KEYWORDS: [&str; 12] = ["infinite", "ponder", "searchmoves", "depth", "nodes", "movetime", "mate", "wtime", "btime", "winc", "binc", "movestogo"];
+
+// any mix of depth, nodes, movetime, clock, mate, infinite, ponder and searchmoves, in any order
+fn parse_go(tokens: &mut Tokens) -> Result<UciCommand, UciError> {
+    if tokens.next_if_eq(&"perft").is_some() {
+        return Ok(UciCommand::Perft(parse_positive("perft", tokens.next())?));
+    }
+    let mut go = GoCommand::default();
+    let mut clock: Option<Clock> = None;
+    while let Some(t) = tokens.next() {
+        match t {
+            "infinite" => go.limits.infinite = true,
+            "ponder" => go.ponder = true,
+            // everything up to the next keyword, moves that can not be played are skipped later
+            "searchmoves" => {
+                while let Some(mv) = tokens.next_if(|t| !GO_KEYWORDS.contains(t)) {
+                    go.search_moves.push(mv.to_owned());
+                }
+            }
+            "depth" => go.limits.depth = Some(parse_positive(t, tokens.next())?),
+            "nodes" => go.limits.nodes = Some(parse_positive(t, tokens.next())?),
+            "movetime" => go.limits.movetime = Some(parse_positive(t, tokens.next())?),
+            "mate" => go.limits.mate = Some(parse_positive(t, tokens.next())?),
+            "wtime" | "btime" | "winc" | "binc" | "movestogo" => {
+                // gui clocks can run negative, that counts as no time left
+                let num = parse_value::<i64>(t, tokens.next())?.max(0) as u64;
+                let clock = clock.get_or_insert_with(Clock::default);
+                match t {
+                    "wtime" => clock.wtime = num,
+                    "btime" => clock.btime = num,
+                    "winc" => clock.winc = num,
+                    "binc" => clock.binc = num,
+                    _ => clock.moves_to_go = Some(num),
+                }
+            }
+            _ => return Err(UciError::UnexpectedToken { command: "go", token: t.to_owned() }),
+        }
+    }
+    go.limits.clock = clock;
+    Ok(UciCommand::Go(go))
+}
+
+// long algebraic notation, e.g. e2e4 or a7a8q
+pub fn is_move(token: &str) -> bool {
+    match token.as_bytes() {
+        [from_file, from_rank, to_file, to_rank, promotion @ ..] => {
+            let square = |file: &u8, rank: &u8| (b'a'..=b'h').contains(file) && (b'1'..=b'8').contains(rank);
+            square(from_file, from_rank) && square(to_file, to_rank) && matches!(promotion, [] | [b'n' | b'b' | b'r' | b'q'])
+        }
+        _ => false,
+    }
+}
+
+fn parse_move(token: &str) -> Result<String, UciError> {
+    if is_move(token) { Ok(token.to_owned()) } else { Err(UciError::InvalidMove(token.to_owned())) }
+}
+
+fn invalid(name: &str, value: &str, expected: &'static str) -> UciError {
+    UciError::InvalidValue { name: name.to_owned(), value: value.to_owned(), expected }
+}
+
+fn missing(name: &str) -> UciError {
+    UciError::MissingValue(name.to_owned())
+}
+
+fn parse_value<T: FromStr>(name: &str, token: Option<&str>) -> Result<T, UciError> {
+    let value = token.ok_or_else(|| missing(name))?;
+    value.parse().map_err(|_| invalid(name, value, "a number"))
+}
+
+fn parse_positive<T: FromStr + PartialOrd + Default>(name: &str, token: Option<&str>) -> Result<T, UciError> {
+    let value = token.ok_or_else(|| missing(name))?;
+    match value.parse::<T>() {
+        Ok(n) if n > T::default() => Ok(n),
+        _ => Err(invalid(name, value, "a positive number")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{UciCommand, UciError, START_POS};
+
+    #[test]
+    fn should_parse_go_limits_in_any_order() {
+        let Ok(UciCommand::Go(go)) = "go wtime -20 ponder searchmoves e2e4 d2d4 depth 8 btime 1000".parse() else {
+            panic!("expected go");
+        };
+        let clock = go.limits.clock.unwrap();
+        assert!(go.ponder && go.search_moves == ["e2e4", "d2d4"]);
+        assert_eq!((go.limits.depth, clock.wtime, clock.btime), (Some(8), 0, 1000));
+        assert!(matches!("go perft 3".parse(), Ok(UciCommand::Perft(3))));
+    }
+
+    #[test]
+    fn should_parse_position_with_moves() {
+        let Ok(UciCommand::Position { fen, moves }) = "position startpos moves e2e4 e7e5 g1f3".parse() else {
+            panic!("expected position");
+        };
+        assert_eq!(fen, START_POS);
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3"]);
+        let Ok(UciCommand::Position { fen, moves }) = "position fen 8/8/8/8/8/8/8/K1k5 b - - 0 1".parse() else {
+            panic!("expected position");
+        };
+        assert_eq!(fen, "8/8/8/8/8/8/8/K1k5 b - - 0 1");
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn should_report_bad_input_as_errors() {
+        let parse = |line: &str| line.parse::<UciCommand>().err();
+        assert_eq!(parse("go depth 5 sideways"), Some(UciError::UnexpectedToken { command: "go", token: "sideways".to_owned() }));
+        assert_eq!(parse("go depth"), Some(UciError::MissingValue("depth".to_owned())));
+        assert_eq!(parse("go nodes -4").map(|e| e.to_string()), Some("Invalid value for 'nodes': '-4', expected a positive number".to_owned()));
+        assert_eq!(parse("position startpos moves e2e4 e9e5"), Some(UciError::InvalidMove("e9e5".to_owned())));
+        assert_eq!(parse("position"), Some(UciError::MissingValue("position".to_owned())));
+        assert_eq!(parse("setoption value 3"), Some(UciError::UnexpectedToken { command: "setoption", token: "value".to_owned() }));
+        assert_eq!(parse("castle now"), Some(UciError::UnknownCommand("castle".to_owned())));
+    }
+}
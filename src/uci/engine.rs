@@ -1,62 +1,187 @@
+use std::io::stdout;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread::{scope, sleep, spawn, JoinHandle};
-use std::time::{Duration, Instant};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{park, spawn, JoinHandle};
 
 use crate::constants::WHITE;
+use crate::evaluation::eval_config::EvalConfig;
 use crate::fen_parsing::parse_fen::{parse_fen, FenError};
 use crate::moving::move_generation::get_mg;
-use crate::moving::move_list::MoveList;
+use crate::moving::mv::Move;
 use crate::position::board::Board;
 use crate::position::zobrist_hashing::ZobristHasher;
-use crate::search::alpha_beta::Searcher;
+use crate::search::alpha_beta::{Searcher, MATE_BOUND};
+use crate::search::clock::SearchClock;
+use crate::search::extensions::ExtensionConfig;
+use crate::search::observer::SearchObserver;
+use crate::search::pruning::PruningConfig;
+use crate::search::skill::{Skill, StrengthConfig};
+use crate::search::transposition::{TTable, DEFAULT_HASH_MB};
+use crate::uci::command::{is_move, UciError};
+use crate::uci::search_limits::{tighter, SearchLimits, TimeLimit};
+use crate::uci::time_manager::TimeManager;
+use crate::uci::uci_observer::UciObserver;
+
+pub const MAX_THREADS: usize = 64;
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 10;
+
+struct SearchThreads {
+    main: Searcher,
+    helpers: Vec<Helper>,
+}
+
+impl SearchThreads {
+    fn for_each(&mut self, mut f: impl FnMut(&mut Searcher)) {
+        f(&mut self.main);
+        for helper in &self.helpers {
+            f(&mut helper.searcher.lock().unwrap());
+        }
+    }
+}
+
+// a thread parked on its job channel between searches, it reports every finished job.
+// dropping the worker closes the channel and waits for the thread to end
+struct Worker<J> {
+    jobs: Option<Sender<J>>,
+    finished: Receiver<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<J: Send + 'static> Worker<J> {
+    fn start(run: impl FnOnce(Receiver<J>, Sender<()>) + Send + 'static) -> Self {
+        let (jobs, job_receiver) = channel();
+        let (finished_sender, finished) = channel();
+        let thread = spawn(move || run(job_receiver, finished_sender));
+        Worker { jobs: Some(jobs), finished, thread: Some(thread) }
+    }
+
+    fn send(&self, job: J) -> bool {
+        self.jobs.as_ref().is_some_and(|jobs| jobs.send(job).is_ok())
+    }
+
+    fn unpark(&self) {
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+}
+
+impl<J> Worker<J> {
+    // the thread leaves its job loop once the channel is closed
+    fn join(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+impl<J> Drop for Worker<J> {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+// a lazy smp searcher with a thread of its own, started on every search of the main thread
+struct Helper {
+    searcher: Arc<Mutex<Searcher>>,
+    worker: Worker<Board>,
+}
+
+impl Helper {
+    fn new(searcher: Searcher, start_depth: i32) -> Self {
+        let searcher = Arc::new(Mutex::new(searcher));
+        let worker = {
+            let searcher = searcher.clone();
+            Worker::start(move |boards, finished| helper_worker(searcher, start_depth, boards, finished))
+        };
+        Helper { searcher, worker }
+    }
+}
+
+// everything the search thread needs besides the searchers, which are prepared before it is sent
+struct SearchJob {
+    board: Board,
+    best_move: Move,
+    max_depth: Option<i32>,
+    time_limit: TimeLimit,
+    skill: Option<Skill>,
+    // node and strength limits only bind the main searcher, so helpers sit those searches out
+    use_helpers: bool,
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+    hold_best_move: bool,
+    observer: Arc<dyn SearchObserver>,
+}
 
 //this holds global state
 pub struct Engine {
     board: Board,
-    mvs: MoveList,
-    searcher: Option<Box<Searcher>>,
+    ttable: Arc<TTable>,
+    hash_mb: usize,
+    // locked by the search thread for the whole search, free whenever no search runs
+    searchers: Arc<Mutex<SearchThreads>>,
+    threads: usize,
+    multi_pv: usize,
+    search_moves: Vec<Move>,
+    extensions: ExtensionConfig,
+    pruning: PruningConfig,
+    eval_config: EvalConfig,
+    qsearch_checks: bool,
+    contempt: i32,
+    move_overhead: u64,
+    strength: StrengthConfig,
+    observer: Arc<dyn SearchObserver>,
     stop: Arc<AtomicBool>,
-    thread: Option<JoinHandle<Box<Searcher>>>
+    ponder: Arc<AtomicBool>,
+    worker: Worker<SearchJob>,
+    running: bool,
+    // the running search holds its bestmove until stop
+    infinite: bool,
 }
 
 impl Default for Engine {
     fn default() -> Self {
+        let ttable = Arc::new(TTable::new(DEFAULT_HASH_MB));
+        let observer: Arc<dyn SearchObserver> = Arc::new(UciObserver::new(Arc::new(Mutex::new(stdout()))));
+        let mut main = Searcher::new_shared(ttable.clone(), true);
+        main.set_observer(observer.clone());
+        let searchers = Arc::new(Mutex::new(SearchThreads { main, helpers: Vec::new() }));
+        let worker = {
+            let searchers = searchers.clone();
+            Worker::start(move |jobs, finished| search_worker(searchers, jobs, finished))
+        };
         Engine {
             board: Board::new(ZobristHasher::new()),
-            mvs: MoveList::new(),
-            searcher: Some(Box::new(Searcher::new())),
+            ttable,
+            hash_mb: DEFAULT_HASH_MB,
+            searchers,
+            threads: 1,
+            multi_pv: 1,
+            search_moves: Vec::new(),
+            extensions: ExtensionConfig::default(),
+            pruning: PruningConfig::default(),
+            eval_config: EvalConfig::default(),
+            qsearch_checks: false,
+            contempt: 0,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            strength: StrengthConfig::default(),
+            observer,
             stop: Arc::new(AtomicBool::new(false)),
-            thread: None
+            ponder: Arc::new(AtomicBool::new(false)),
+            worker,
+            running: false,
+            infinite: false,
         }
     }
 }
 
-#[derive(Clone, Copy)]
-enum SearchLimit {
-    Depth(i32),
-    Nodes(u64),
-    Time(u128),
-    Infinite,
-}
-
-impl SearchLimit {
-    fn soft_stop(&self, nodes_searched: u64, depth_reached: i32, time_passed: u128) -> bool {
-        match self {
-            Self::Infinite => false,
-            Self::Depth(num) => depth_reached > *num,
-            Self::Nodes(nodes) => nodes_searched >= *nodes,
-            Self::Time(t) => time_passed >= *t / 2,
-        }
-    }
-
-    fn hard_stop(&self, time_passed: u128) -> bool {
-        match self {
-            Self::Infinite => false,
-            Self::Depth(_) => false,
-            Self::Nodes(_) => false,
-            Self::Time(t) => time_passed >= *t,
-        }
+impl Drop for Engine {
+    // the helpers are joined right after, when the searchers drop with the engine
+    fn drop(&mut self) {
+        self.stop();
+        self.worker.join();
     }
 }
 
@@ -65,130 +190,259 @@ impl Engine {
         Self::default()
     }
 
+    // returns once the running search has sent its bestmove
     pub fn stop(&mut self) {
-        let t = self.thread.take();
-        if let Some(th) = t {
+        if self.running {
             self.stop.store(true, Ordering::Relaxed);
-            let res = th.join();
-            match res {
-                Ok(s) => self.searcher = Some(s),
-                _ => panic!("Error in search")
-            }
+            self.worker.unpark();
+            _ = self.worker.finished.recv();
+            self.running = false;
         }
     }
 
-    pub fn set_pos(&mut self, fen: &str) -> Result<(), FenError> {
-        self.mvs.reset();
-        let new_board = parse_fen(fen)?;
-        self.board = new_board;
+    // a search that ends by itself is waited for, one that waits for stop or ponderhit is stopped
+    pub fn finish(&mut self) {
+        if self.running && !self.infinite && !self.ponder.load(Ordering::Relaxed) {
+            _ = self.worker.finished.recv();
+            self.running = false;
+        }
+        self.stop();
+    }
+
+    // total number of search threads, including the main one
+    pub fn set_threads(&mut self, threads: usize) {
+        self.stop();
+        self.threads = threads.clamp(1, MAX_THREADS);
+        let searchers = &mut *self.searchers.lock().unwrap();
+        let shared_nodes = searchers.main.shared_nodes();
+        searchers.helpers = (1..self.threads)
+            .map(|i| {
+                let mut helper = Searcher::new_shared(self.ttable.clone(), false);
+                helper.set_extensions(self.extensions);
+                helper.set_pruning(self.pruning);
+                helper.set_eval_config(self.eval_config);
+                helper.set_qsearch_checks(self.qsearch_checks);
+                helper.set_contempt(self.contempt);
+                helper.set_shared_nodes(shared_nodes.clone());
+                // odd helpers run one ply ahead and each takes the root moves in its own order,
+                // so threads desynchronize
+                helper.set_root_rotation(i);
+                Helper::new(helper, 1 + i as i32 % 2)
+            })
+            .collect();
+    }
+
+    // a size that can not be allocated leaves the previous table in place, the error names its size
+    pub fn set_hash(&mut self, size_mb: usize) -> Result<(), String> {
+        self.stop();
+        let Ok(ttable) = TTable::try_new(size_mb) else {
+            return Err(format!("Could not allocate {size_mb} MB for Hash, keeping {} MB", self.hash_mb));
+        };
+        self.hash_mb = size_mb;
+        let ttable = Arc::new(ttable);
+        self.ttable = ttable.clone();
+        self.for_each_searcher(|s| s.set_ttable(ttable.clone()));
         Ok(())
     }
 
-    pub fn make_move(&mut self, mv_s: &str) -> Result<(), String> {
-        self.mvs = get_mg().generate_moves(&self.board);
-        for mv in self.mvs.iter() {
-            let mv_str = mv.to_str();
-            if mv_str == mv_s {
-                self.board.make_move(mv);
-                return Ok(());
-            }
+    pub fn get_hash(&self) -> usize {
+        self.hash_mb
+    }
+
+    pub fn clear_hash(&mut self) {
+        self.stop();
+        self.ttable.clear();
+    }
+
+    pub fn new_game(&mut self) {
+        self.clear_hash();
+        self.for_each_searcher(|s| s.new_game());
+    }
+
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.stop();
+        self.multi_pv = multi_pv;
+        self.searchers.lock().unwrap().main.set_multi_pv(multi_pv);
+    }
+
+    pub fn get_multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    pub fn set_extensions(&mut self, extensions: ExtensionConfig) {
+        self.extensions = extensions;
+        self.for_each_searcher(|s| s.set_extensions(extensions));
+    }
+
+    pub fn get_extensions(&self) -> ExtensionConfig {
+        self.extensions
+    }
+
+    pub fn set_pruning(&mut self, pruning: PruningConfig) {
+        self.pruning = pruning;
+        self.for_each_searcher(|s| s.set_pruning(pruning));
+    }
+
+    pub fn get_pruning(&self) -> PruningConfig {
+        self.pruning
+    }
+
+    // the table caches static evals, those of the old weights must not outlive them
+    pub fn set_eval_config(&mut self, config: EvalConfig) {
+        self.eval_config = config;
+        self.for_each_searcher(|s| s.set_eval_config(config));
+        self.ttable.clear();
+    }
+
+    pub fn get_eval_config(&self) -> EvalConfig {
+        self.eval_config
+    }
+
+    pub fn set_qsearch_checks(&mut self, qsearch_checks: bool) {
+        self.qsearch_checks = qsearch_checks;
+        self.for_each_searcher(|s| s.set_qsearch_checks(qsearch_checks));
+    }
+
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.contempt = contempt;
+        self.for_each_searcher(|s| s.set_contempt(contempt));
+    }
+
+    // the next search runs without limits until ponderhit, applies to the next search only.
+    // ignored while a search runs, ponderhit has to reach the flag that search started with
+    pub fn set_ponder(&mut self, ponder: bool) {
+        if !self.is_running() {
+            self.ponder = Arc::new(AtomicBool::new(ponder));
         }
-        Err(format!("Move {mv_s} not found"))
     }
 
-    pub fn search_movetime(&mut self, movetime: u64) {
-        let time = movetime as u128;
-        let sl = SearchLimit::Time(time);
-        self.run(sl);
+    // the predicted move was played, the search goes on under its time limits from now
+    pub fn ponderhit(&mut self) {
+        self.ponder.store(false, Ordering::Relaxed);
+        self.worker.unpark();
     }
 
-    pub fn search_nodes(&mut self, nodes: u64) {
-        let sl = SearchLimit::Nodes(nodes);
-        self.run(sl);
+    // time kept back per move for communication with the gui
+    pub fn set_move_overhead(&mut self, move_overhead: u64) {
+        self.move_overhead = move_overhead;
     }
 
-    pub fn search_to_depth(&mut self, depth: i32) {
-        let sl = SearchLimit::Depth(depth);
-        self.run(sl);
+    pub fn set_strength(&mut self, strength: StrengthConfig) {
+        self.strength = strength;
     }
 
-    pub fn search_with_time(&mut self, wtime: u64, btime: u64, winc: u64, binc: u64) {
-        let sl = if self.board.us == WHITE {
-            self.calc_time(wtime, winc)
-        } else {
-            self.calc_time(btime, binc)
-        };
-        self.run(sl);
+    pub fn get_strength(&self) -> StrengthConfig {
+        self.strength
     }
 
-    pub fn search_infinite(&mut self) {
-        let sl = SearchLimit::Infinite;
-        self.run(sl);
+    // receives info and bestmove from every following search, uci output by default
+    pub fn set_observer(&mut self, observer: Arc<dyn SearchObserver>) {
+        self.stop();
+        self.searchers.lock().unwrap().main.set_observer(observer.clone());
+        self.observer = observer;
     }
 
-    fn calc_time(&self, time: u64, inc: u64) -> SearchLimit {
-        let to_use = (time / 20 + inc / 2) as u128;
-        SearchLimit::Time(to_use)
+    fn for_each_searcher(&mut self, f: impl Fn(&mut Searcher)) {
+        self.stop();
+        self.searchers.lock().unwrap().for_each(f);
     }
 
-    pub fn is_running(&mut self) -> bool {
-        if let Some(t) = &self.thread {
-            !&t.is_finished()
-        } else {
-            false
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn set_pos(&mut self, fen: &str) -> Result<(), FenError> {
+        let new_board = parse_fen(fen)?;
+        self.board = new_board;
+        Ok(())
+    }
+
+    // the position only changes when the fen and every move in it are valid,
+    // a search on the old position is stopped either way
+    pub fn set_position(&mut self, fen: &str, moves: &[String]) -> Result<(), UciError> {
+        self.stop();
+        let mut board = parse_fen(fen).map_err(UciError::InvalidFen)?;
+        for mv_s in moves {
+            let mv = find_move(&board, mv_s)?;
+            board.make_move(&mv);
         }
+        self.board = board;
+        Ok(())
+    }
+
+    // applies to the next search only. moves that can not be played are left out and returned,
+    // when none is left every legal move is searched
+    pub fn set_search_moves(&mut self, moves: &[String]) -> Vec<UciError> {
+        let mut skipped = Vec::new();
+        self.search_moves = moves.iter().filter_map(|mv_s| {
+            let mv = if is_move(mv_s) { find_move(&self.board, mv_s) } else { Err(UciError::InvalidMove(mv_s.clone())) };
+            mv.map_err(|e| skipped.push(e)).ok()
+        }).collect();
+        skipped
+    }
+
+    // the clock and movetime are ignored for infinite searches
+    pub fn search(&mut self, limits: SearchLimits) {
+        let movetime = limits.movetime.filter(|_| !limits.infinite)
+            .map(|t| t.saturating_sub(self.move_overhead).max(1) as u128);
+        let time_manager = limits.clock.filter(|_| !limits.infinite).map(|clock| {
+            let (time, inc) = if self.board.us == WHITE { (clock.wtime, clock.winc) } else { (clock.btime, clock.binc) };
+            let legal_moves = get_mg().generate_moves(&self.board).get_count();
+            TimeManager::new(time, inc, clock.moves_to_go, self.move_overhead, legal_moves)
+        });
+        self.run(limits, TimeLimit::new(movetime, time_manager));
     }
 
-    fn run(&mut self, limit: SearchLimit) {
-        if self.is_running() {
-            return;
+    pub fn is_running(&mut self) -> bool {
+        if self.running && self.worker.finished.try_recv().is_ok() {
+            self.running = false;
         }
-        self.stop();
+        self.running
+    }
 
-        if self.searcher.is_some() {
-            let mut searcher = self.searcher.take().unwrap();
-            self.stop = Arc::new(AtomicBool::new(false));
-            let stop = self.stop.clone();
-            let mut best_mv = *get_mg().generate_moves(&self.board).get_move(0);
-            let mut board = self.board.clone();
-            let t = spawn(move || {
-                searcher.prepare_search(stop.clone());
-                let searcher = scope(|s| {
-                    let start = Instant::now();
-                    let stop = stop.clone();
-                    let stop2 = stop.clone();
-                    let handle = s.spawn(move || {
-                        let mut nodes_searched = 0;
-                        let mut depth= 1;
-                        let mut score = 0;
-                        while !limit.soft_stop(nodes_searched, depth, start.elapsed().as_millis()) 
-                        && !stop2.load(std::sync::atomic::Ordering::Relaxed) && score < 900_000
-                        {
-                            let (ts, tm) = searcher.make_search(&mut board, depth);
-                            if !stop2.load(Ordering::Relaxed) {
-                                score = ts;
-                                best_mv = tm;
-                                depth += 1;
-                                nodes_searched += searcher.get_nodes_searched();
-                            }
-                        }
-                        (searcher, best_mv)
-                    });
-                    while !limit.hard_stop(start.elapsed().as_millis()) && !stop.load(Ordering::Relaxed) && !handle.is_finished() {
-                        sleep(Duration::from_millis(2));
-                    }
-                    stop.store(true, Ordering::Relaxed);
-                    let (searcher, best_mv) = handle.join().unwrap();
-                    println!("bestmove {}", best_mv.to_str());
-                    searcher
-                });
-                searcher
+    // a search that is still running is stopped and sends its bestmove first
+    fn run(&mut self, limits: SearchLimits, time_limit: TimeLimit) {
+        self.stop();
+        // bounds from earlier, pruned searches could hide a mate
+        if limits.mate.is_some() {
+            self.ttable.clear();
+        }
+        let search_moves = std::mem::take(&mut self.search_moves);
+        // stays null when there is no legal move, which is sent as 0000
+        let best_move = search_moves.first().copied()
+            .or_else(|| get_mg().generate_moves(&self.board).iter().next().copied())
+            .unwrap_or(Move::null());
+        self.stop = Arc::new(AtomicBool::new(false));
+        // a weakened search looks at a few candidates and picks one of them at random
+        let skill = self.strength.skill();
+        {
+            let searchers = &mut *self.searchers.lock().unwrap();
+            let main = &mut searchers.main;
+            main.set_multi_pv(skill.map_or(self.multi_pv, |s| s.multi_pv().max(self.multi_pv)));
+            main.set_node_limit(tighter(limits.nodes, skill.map(|s| s.max_nodes())));
+            main.set_time_limit(SearchClock::new(self.ponder.clone()), time_limit.hard_limit());
+            searchers.for_each(|searcher| {
+                searcher.prepare_search(self.stop.clone());
+                searcher.set_root_moves(search_moves.clone());
+                searcher.set_full_width(limits.mate.is_some());
             });
-            self.thread = Some(t);
         }
+        let job = SearchJob {
+            board: self.board.clone(),
+            best_move,
+            max_depth: tighter(limits.max_depth(), skill.map(|s| s.max_depth())),
+            time_limit,
+            skill,
+            use_helpers: limits.nodes.is_none() && skill.is_none(),
+            stop: self.stop.clone(),
+            ponder: self.ponder.clone(),
+            hold_best_move: limits.infinite,
+            observer: self.observer.clone(),
+        };
+        self.running = self.worker.send(job);
+        self.infinite = limits.infinite;
     }
 
-
     pub fn get_board(&self) -> &Board {
         &self.board
     }
@@ -197,3 +451,209 @@ impl Engine {
         &mut self.board
     }
 }
+
+// lives as long as the engine and runs one search per job
+fn search_worker(searchers: Arc<Mutex<SearchThreads>>, jobs: Receiver<SearchJob>, finished: Sender<()>) {
+    for job in jobs {
+        let observer = job.observer.clone();
+        let (best_move, ponder_move) = run_search(&mut searchers.lock().unwrap(), job);
+        observer.on_best_move(best_move, ponder_move);
+        // the engine may reconfigure the searchers as soon as it hears back
+        if finished.send(()).is_err() {
+            break;
+        }
+    }
+}
+
+fn helper_worker(searcher: Arc<Mutex<Searcher>>, start_depth: i32, boards: Receiver<Board>, finished: Sender<()>) {
+    for mut board in boards {
+        searcher.lock().unwrap().search_helper(&mut board, start_depth);
+        if finished.send(()).is_err() {
+            break;
+        }
+    }
+}
+
+// iterative deepening on the main searcher while the helpers search the same position,
+// node and hard time limits are checked inside the search itself
+fn run_search(searchers: &mut SearchThreads, job: SearchJob) -> (Move, Option<Move>) {
+    let SearchJob { mut board, mut best_move, max_depth, mut time_limit, skill, use_helpers, stop, ponder, hold_best_move, .. } = job;
+    let SearchThreads { main, helpers } = searchers;
+    // without a legal move there is nothing to search, bestmove 0000 goes out at once
+    let searchable = !best_move.is_null();
+    let started: Vec<&Helper> = helpers.iter().filter(|helper| searchable && use_helpers && helper.worker.send(board.clone())).collect();
+    let mut depth = 1;
+    let mut score = 0;
+    while searchable && !main.elapsed().is_some_and(|t| time_limit.soft_stop(t))
+    && !stop.load(Ordering::Relaxed) && score <= MATE_BOUND
+    && max_depth.is_none_or(|max| depth <= max)
+    {
+        let (ts, tm) = main.make_search(&mut board, depth);
+        if !stop.load(Ordering::Relaxed) {
+            score = ts;
+            best_move = tm;
+            time_limit.update(score, best_move);
+            depth += 1;
+        }
+    }
+    if let Some(picked) = skill.and_then(|s| s.pick_move(main.get_root_lines(), &mut rand::rng())) {
+        best_move = picked;
+    }
+    // bestmove must not be sent before ponderhit or stop, even when the search is done
+    while (ponder.load(Ordering::Relaxed) || hold_best_move) && !stop.load(Ordering::Relaxed) {
+        park();
+    }
+    stop.store(true, Ordering::Relaxed);
+    // the helpers saw stop too, their searchers are free again once they report back
+    for helper in started {
+        _ = helper.worker.finished.recv();
+    }
+    let ponder_move = main.ponder_move(&best_move);
+    (best_move, ponder_move)
+}
+
+fn find_move(board: &Board, mv_s: &str) -> Result<Move, UciError> {
+    let legal = get_mg().generate_moves(board);
+    legal.iter().find(|mv| mv.to_str() == mv_s).copied().ok_or_else(|| UciError::IllegalMove(mv_s.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::Engine;
+    use crate::evaluation::eval_config::EvalConfig;
+    use crate::uci::command::{UciError, START_POS};
+    use crate::uci::search_limits::SearchLimits;
+    use crate::search::observer::{Bound, Score};
+    use crate::tests::common::Recorder;
+
+    #[test]
+    fn should_report_search_progress_to_observer() {
+        let recorder = Arc::new(Recorder::default());
+        let mut engine = Engine::default();
+        engine.set_observer(recorder.clone());
+        engine.set_pos(START_POS).unwrap();
+        engine.search(SearchLimits::depth(4));
+        while engine.is_running() {
+            sleep(Duration::from_millis(5));
+        }
+        engine.stop();
+        let infos = recorder.infos.lock().unwrap();
+        let exact: Vec<_> = infos.iter().filter(|info| info.bound == Bound::Exact).collect();
+        assert_eq!(exact.iter().map(|info| info.depth).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        let last = exact.last().unwrap();
+        assert!(last.seldepth >= last.depth && last.nodes > 0);
+        assert!(*recorder.best_move.lock().unwrap() == last.pv.first().copied());
+    }
+
+    #[test]
+    fn should_forget_cached_evals_when_eval_weights_change() {
+        let mut engine = Engine::default();
+        engine.set_observer(Arc::new(Recorder::default()));
+        engine.set_pos(START_POS).unwrap();
+        engine.search(SearchLimits::depth(6));
+        engine.finish();
+        assert!(engine.ttable.hashfull() > 0);
+        engine.set_eval_config(EvalConfig { mobility: 0, ..engine.get_eval_config() });
+        assert_eq!(engine.ttable.hashfull(), 0);
+    }
+
+    #[test]
+    fn should_keep_position_when_a_move_is_illegal() {
+        let mut engine = Engine::default();
+        engine.set_position(START_POS, &["e2e4".to_owned()]).unwrap();
+        let hash = engine.get_board().get_hash();
+        let moves = ["e2e4".to_owned(), "e7e5".to_owned(), "e4e5".to_owned()];
+        assert_eq!(engine.set_position(START_POS, &moves), Err(UciError::IllegalMove("e4e5".to_owned())));
+        assert_eq!(engine.get_board().get_hash(), hash);
+    }
+
+    #[test]
+    fn should_stop_running_search_when_a_new_one_starts() {
+        let recorder = Arc::new(Recorder::default());
+        let mut engine = Engine::default();
+        engine.set_observer(recorder.clone());
+        engine.set_pos(START_POS).unwrap();
+        engine.search(SearchLimits { infinite: true, ..Default::default() });
+        engine.search(SearchLimits::depth(3));
+        // the infinite search already answered, the new one may have too
+        assert!(*recorder.best_moves.lock().unwrap() >= 1);
+        while engine.is_running() {
+            sleep(Duration::from_millis(5));
+        }
+        assert_eq!(*recorder.best_moves.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn should_let_ponderhit_reach_the_running_search() {
+        let mut engine = Engine::default();
+        engine.set_observer(Arc::new(Recorder::default()));
+        engine.set_pos(START_POS).unwrap();
+        engine.set_ponder(true);
+        engine.search(SearchLimits::depth(2));
+        engine.set_ponder(true);
+        engine.ponderhit();
+        for _ in 0..400 {
+            if !engine.is_running() {
+                break;
+            }
+            sleep(Duration::from_millis(5));
+        }
+        assert!(!engine.is_running());
+    }
+
+    #[test]
+    fn should_find_mate_within_the_mate_limit() {
+        let recorder = Arc::new(Recorder::default());
+        let mut engine = Engine::default();
+        engine.set_observer(recorder.clone());
+        engine.set_pos("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1").unwrap();
+        engine.search(SearchLimits { mate: Some(2), ..Default::default() });
+        while engine.is_running() {
+            sleep(Duration::from_millis(5));
+        }
+        let infos = recorder.infos.lock().unwrap();
+        let last = infos.iter().rfind(|info| info.bound == Bound::Exact).unwrap();
+        assert_eq!(last.score, Score::Mate(2));
+    }
+
+    #[test]
+    fn should_repeat_node_limited_searches_with_several_threads() {
+        let mut results = Vec::new();
+        for _ in 0..2 {
+            let recorder = Arc::new(Recorder::default());
+            let mut engine = Engine::default();
+            engine.set_observer(recorder.clone());
+            engine.set_threads(4);
+            engine.set_pos(START_POS).unwrap();
+            engine.search(SearchLimits::nodes(30_000));
+            while engine.is_running() {
+                sleep(Duration::from_millis(5));
+            }
+            let infos = recorder.infos.lock().unwrap();
+            let last = infos.last().unwrap();
+            results.push((last.nodes, last.pv.clone(), *recorder.best_move.lock().unwrap()));
+        }
+        assert!(results[0] == results[1]);
+    }
+
+    #[test]
+    fn should_reuse_helper_threads_across_searches() {
+        let recorder = Arc::new(Recorder::default());
+        let mut engine = Engine::default();
+        engine.set_observer(recorder.clone());
+        engine.set_threads(3);
+        engine.set_pos(START_POS).unwrap();
+        for _ in 0..3 {
+            engine.search(SearchLimits { infinite: true, ..Default::default() });
+            sleep(Duration::from_millis(20));
+            engine.stop();
+        }
+        assert_eq!(*recorder.best_moves.lock().unwrap(), 3);
+        // dropping joins the search thread and its helpers
+        drop(engine);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::moving::mv::Move;
+use crate::search::observer::{Bound, Score, SearchInfo};
+use crate::uci::options::UciOption;
+
+// where everything meant for the gui goes, shared by the controller and the search thread
+pub type Output = Arc<Mutex<dyn Write + Send>>;
+
+// a gui that stopped listening is no reason to stop the engine, so write errors are dropped
+pub fn write_line(output: &Output, line: impl fmt::Display) {
+    let mut output = output.lock().unwrap();
+    _ = writeln!(output, "{line}").and_then(|_| output.flush());
+}
+
+// an engine to gui message, displaying it gives the line to send
+pub enum UciMessage<'a> {
+    Id { name: &'a str, author: &'a str },
+    Option(&'a UciOption),
+    UciOk,
+    ReadyOk,
+    BestMove { best_move: Move, ponder_move: Option<Move> },
+    Info(&'a SearchInfo),
+    // free text for the gui to show, also how bad input is reported
+    InfoString(&'a str),
+}
+
+impl fmt::Display for UciMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciMessage::Id { name, author } => write!(f, "id name {name}\nid author {author}"),
+            UciMessage::Option(option) => write!(f, "{}", option.to_uci()),
+            UciMessage::UciOk => write!(f, "uciok"),
+            UciMessage::ReadyOk => write!(f, "readyok"),
+            UciMessage::BestMove { best_move, ponder_move } => {
+                // a null move means there was nothing to play, uci calls it 0000
+                let best_move = if best_move.is_null() { "0000".to_owned() } else { best_move.to_str() };
+                write!(f, "bestmove {best_move}")?;
+                match ponder_move {
+                    Some(ponder_move) => write!(f, " ponder {}", ponder_move.to_str()),
+                    None => Ok(()),
+                }
+            }
+            UciMessage::Info(info) => {
+                let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_str()).collect();
+                write!(
+                    f,
+                    "info depth {} seldepth {} multipv {} score {}{} nodes {} nps {} time {} hashfull {} pv {}",
+                    info.depth, info.seldepth, info.multipv, format_score(info.score), format_bound(info.bound),
+                    info.nodes, info.nps, info.time, info.hashfull, pv.join(" ")
+                )
+            }
+            // a line break would end the message early
+            UciMessage::InfoString(text) => write!(f, "info string {}", text.replace('\n', " ")),
+        }
+    }
+}
+
+fn format_score(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("cp {cp}"),
+        Score::Mate(moves) => format!("mate {moves}"),
+    }
+}
+
+fn format_bound(bound: Bound) -> &'static str {
+    match bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UciMessage;
+    use crate::moving::mv::Move;
+    use crate::search::observer::{Bound, Score, SearchInfo};
+
+    #[test]
+    fn should_serialize_engine_messages() {
+        let (e2e4, e7e5) = (Move::new_quiet(12, 28), Move::new_quiet(52, 36));
+        let info = SearchInfo {
+            depth: 3, seldepth: 5, multipv: 1, score: Score::Mate(-2), bound: Bound::Lower,
+            nodes: 900, nps: 9000, time: 100, hashfull: 1, pv: vec![e2e4, e7e5],
+        };
+        assert_eq!(
+            UciMessage::Info(&info).to_string(),
+            "info depth 3 seldepth 5 multipv 1 score mate -2 lowerbound nodes 900 nps 9000 time 100 hashfull 1 pv e2e4 e7e5"
+        );
+        assert_eq!(UciMessage::BestMove { best_move: e2e4, ponder_move: Some(e7e5) }.to_string(), "bestmove e2e4 ponder e7e5");
+        assert_eq!(UciMessage::InfoString("bad\nfen").to_string(), "info string bad fen");
+        assert_eq!(UciMessage::BestMove { best_move: Move::null(), ponder_move: None }.to_string(), "bestmove 0000");
+    }
+}
@@ -0,0 +1,189 @@
+use crate::evaluation::eval_config::EvalConfig;
+use crate::search::alpha_beta::MAX_MULTI_PV;
+use crate::search::extensions::ExtensionConfig;
+use crate::search::pruning::PruningConfig;
+use crate::search::skill::{StrengthConfig, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+use crate::search::transposition::{DEFAULT_HASH_MB, MAX_HASH_MB};
+use crate::uci::engine::{Engine, DEFAULT_MOVE_OVERHEAD, MAX_THREADS};
+
+const MAX_PRUNING_VALUE: i64 = 1000;
+const MAX_CONTEMPT: i64 = 200;
+const MAX_MOVE_OVERHEAD: i64 = 5000;
+const MAX_EVAL_WEIGHT: i64 = 400;
+
+// every kind carries its own setter, so a value reaches the engine only after it was checked.
+// a spin setter may still refuse a value in range, like a Hash size that can not be allocated
+pub enum OptionKind {
+    Spin { default: i64, min: i64, max: i64, set: fn(&mut Engine, i64) -> Result<(), String> },
+    Check { default: bool, set: fn(&mut Engine, bool) },
+    Combo { default: &'static str, vars: &'static [&'static str], set: fn(&mut Engine, &str) },
+    Button { press: fn(&mut Engine) },
+    String { default: &'static str, set: fn(&mut Engine, &str) },
+}
+
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+impl UciOption {
+    pub fn spin(name: &'static str, default: i64, min: i64, max: i64, set: fn(&mut Engine, i64) -> Result<(), String>) -> Self {
+        UciOption { name, kind: OptionKind::Spin { default, min, max, set } }
+    }
+
+    pub fn check(name: &'static str, default: bool, set: fn(&mut Engine, bool)) -> Self {
+        UciOption { name, kind: OptionKind::Check { default, set } }
+    }
+
+    pub fn button(name: &'static str, press: fn(&mut Engine)) -> Self {
+        UciOption { name, kind: OptionKind::Button { press } }
+    }
+
+    // the line sent for this option in the uci response
+    pub fn to_uci(&self) -> String {
+        let name = self.name;
+        match &self.kind {
+            OptionKind::Spin { default, min, max, .. } => format!("option name {name} type spin default {default} min {min} max {max}"),
+            OptionKind::Check { default, .. } => format!("option name {name} type check default {default}"),
+            OptionKind::Combo { default, vars, .. } => {
+                let vars: String = vars.iter().map(|v| format!(" var {v}")).collect();
+                format!("option name {name} type combo default {default}{vars}")
+            }
+            OptionKind::Button { .. } => format!("option name {name} type button"),
+            OptionKind::String { default, .. } => format!("option name {name} type string default {default}"),
+        }
+    }
+
+    pub fn apply(&self, engine: &mut Engine, value: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid value for {}: '{value}'", self.name);
+        match &self.kind {
+            OptionKind::Spin { min, max, set, .. } => match value.parse::<i64>() {
+                Ok(n) if (*min..=*max).contains(&n) => set(engine, n)?,
+                _ => return Err(format!("{}, expected {min} to {max}", invalid())),
+            },
+            OptionKind::Check { set, .. } => match value.to_lowercase().parse::<bool>() {
+                Ok(on) => set(engine, on),
+                _ => return Err(format!("{}, expected true or false", invalid())),
+            },
+            OptionKind::Combo { vars, set, .. } => match vars.iter().find(|v| v.eq_ignore_ascii_case(value)) {
+                Some(var) => set(engine, var),
+                None => return Err(format!("{}, expected one of {}", invalid(), vars.join(", "))),
+            },
+            OptionKind::Button { press } => press(engine),
+            // "<empty>" is the uci way of sending an empty string
+            OptionKind::String { set, .. } => set(engine, if value == "<empty>" { "" } else { value }),
+        }
+        Ok(())
+    }
+}
+
+pub struct OptionRegistry {
+    options: Vec<UciOption>,
+}
+
+impl Default for OptionRegistry {
+    fn default() -> Self {
+        OptionRegistry::new(engine_options())
+    }
+}
+
+impl OptionRegistry {
+    pub fn new(options: Vec<UciOption>) -> Self {
+        OptionRegistry { options }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &UciOption> {
+        self.options.iter()
+    }
+
+    // option names are case insensitive
+    pub fn set(&self, engine: &mut Engine, name: &str, value: &str) -> Result<(), String> {
+        match self.options.iter().find(|o| o.name.eq_ignore_ascii_case(name)) {
+            Some(option) => option.apply(engine, value),
+            None => Err(format!("Unknown option: '{name}'")),
+        }
+    }
+}
+
+fn engine_options() -> Vec<UciOption> {
+    let pruning = PruningConfig::default();
+    let eval = EvalConfig::default();
+    vec![
+        UciOption::spin("Threads", 1, 1, MAX_THREADS as i64, |e, n| { e.set_threads(n as usize); Ok(()) }),
+        UciOption::spin("Hash", DEFAULT_HASH_MB as i64, 1, MAX_HASH_MB as i64, |e, n| e.set_hash(n as usize)),
+        UciOption::button("Clear Hash", |e| e.clear_hash()),
+        // pondering is driven by the gui, the option only tells it that we can
+        UciOption::check("Ponder", false, |_, _| {}),
+        UciOption::spin("Skill Level", MAX_SKILL_LEVEL as i64, 0, MAX_SKILL_LEVEL as i64, |e, n| {
+            e.set_strength(StrengthConfig { skill_level: n as i32, ..e.get_strength() });
+            Ok(())
+        }),
+        UciOption::check("UCI_LimitStrength", false, |e, on| {
+            e.set_strength(StrengthConfig { limit_strength: on, ..e.get_strength() })
+        }),
+        UciOption::spin("UCI_Elo", MAX_ELO as i64, MIN_ELO as i64, MAX_ELO as i64, |e, n| {
+            e.set_strength(StrengthConfig { elo: n as i32, ..e.get_strength() });
+            Ok(())
+        }),
+        UciOption::spin("MultiPV", 1, 1, MAX_MULTI_PV as i64, |e, n| { e.set_multi_pv(n as usize); Ok(()) }),
+        UciOption::check("Check Extension", true, |e, on| e.set_extensions(ExtensionConfig { check: on, ..e.get_extensions() })),
+        UciOption::check("Single Reply Extension", true, |e, on| e.set_extensions(ExtensionConfig { single_reply: on, ..e.get_extensions() })),
+        UciOption::check("Recapture Extension", false, |e, on| e.set_extensions(ExtensionConfig { recapture: on, ..e.get_extensions() })),
+        UciOption::check("Passed Pawn Extension", true, |e, on| e.set_extensions(ExtensionConfig { passed_pawn: on, ..e.get_extensions() })),
+        UciOption::check("Singular Extension", true, |e, on| e.set_extensions(ExtensionConfig { singular: on, ..e.get_extensions() })),
+        UciOption::spin("Move Overhead", DEFAULT_MOVE_OVERHEAD as i64, 0, MAX_MOVE_OVERHEAD, |e, n| { e.set_move_overhead(n as u64); Ok(()) }),
+        UciOption::spin("Contempt", 0, -MAX_CONTEMPT, MAX_CONTEMPT, |e, n| { e.set_contempt(n as i32); Ok(()) }),
+        UciOption::check("QSearch Checks", false, |e, on| e.set_qsearch_checks(on)),
+        UciOption::spin("RFP Margin", pruning.rfp_margin as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { rfp_margin: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("RFP Depth", pruning.rfp_max_depth as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { rfp_max_depth: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("Futility Margin", pruning.futility_margin as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { futility_margin: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("Futility Depth", pruning.futility_max_depth as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { futility_max_depth: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("Razor Margin", pruning.razor_margin as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { razor_margin: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("Razor Depth", pruning.razor_max_depth as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { razor_max_depth: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("LMP Base", pruning.lmp_base as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { lmp_base: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("LMP Depth", pruning.lmp_max_depth as i64, 0, MAX_PRUNING_VALUE, |e, n| { e.set_pruning(PruningConfig { lmp_max_depth: n as i32, ..e.get_pruning() }); Ok(()) }),
+        UciOption::spin("Mobility Weight", eval.mobility as i64, 0, MAX_EVAL_WEIGHT, |e, n| { e.set_eval_config(EvalConfig { mobility: n as i32, ..e.get_eval_config() }); Ok(()) }),
+        UciOption::spin("King Safety Weight", eval.king_safety as i64, 0, MAX_EVAL_WEIGHT, |e, n| { e.set_eval_config(EvalConfig { king_safety: n as i32, ..e.get_eval_config() }); Ok(()) }),
+        UciOption::spin("Pawn Structure Weight", eval.pawn_structure as i64, 0, MAX_EVAL_WEIGHT, |e, n| { e.set_eval_config(EvalConfig { pawn_structure: n as i32, ..e.get_eval_config() }); Ok(()) }),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OptionKind, OptionRegistry, UciOption};
+    use crate::uci::engine::Engine;
+
+    #[test]
+    fn should_reject_values_outside_option_type() {
+        let registry = OptionRegistry::default();
+        let mut engine = Engine::new();
+        assert!(registry.set(&mut engine, "multipv", "3").is_ok());
+        assert_eq!(engine.get_multi_pv(), 3);
+        assert!(registry.set(&mut engine, "MultiPV", "0").is_err());
+        assert!(registry.set(&mut engine, "Ponder", "maybe").is_err());
+        assert!(registry.set(&mut engine, "No Such Option", "1").is_err());
+        assert_eq!(engine.get_multi_pv(), 3);
+    }
+
+    #[test]
+    fn should_report_the_hash_size_in_use_when_allocation_fails() {
+        let registry = OptionRegistry::new(vec![UciOption::spin("Hash", 16, 1, i64::MAX, |e, n| e.set_hash(n as usize))]);
+        let mut engine = Engine::new();
+        let too_big = (usize::MAX / (1024 * 1024)).to_string();
+        let error = registry.set(&mut engine, "Hash", &too_big).unwrap_err();
+        assert!(error.ends_with(&format!("keeping {} MB", engine.get_hash())));
+    }
+
+    #[test]
+    fn should_advertise_combo_and_string_options() {
+        let registry = OptionRegistry::new(vec![
+            UciOption { name: "Style", kind: OptionKind::Combo { default: "Normal", vars: &["Solid", "Normal"], set: |_, _| {} } },
+            UciOption { name: "Log File", kind: OptionKind::String { default: "<empty>", set: |_, _| {} } },
+        ]);
+        let lines: Vec<String> = registry.iter().map(|o| o.to_uci()).collect();
+        assert_eq!(lines, ["option name Style type combo default Normal var Solid var Normal", "option name Log File type string default <empty>"]);
+        let mut engine = Engine::new();
+        assert!(registry.set(&mut engine, "style", "solid").is_ok());
+        assert!(registry.set(&mut engine, "style", "wild").is_err());
+    }
+}
@@ -1,12 +1,14 @@
 use std::time::Instant;
 
 use crate::{
-    moving::move_generation::MoveGenerator, position::board::Board
+    moving::{move_generation::MoveGenerator, mv::Move}, position::board::Board
 };
 
 pub struct PerftResult {
     pub time: f32,
     pub result: usize,
+    // nodes below every root move, for the caller to print
+    pub divide: Vec<(Move, usize)>,
 }
 
 pub fn make_perft(depth: i32, board: &mut Board) -> PerftResult {
@@ -14,6 +16,7 @@ pub fn make_perft(depth: i32, board: &mut Board) -> PerftResult {
 
     let start = Instant::now();
     let mut result = 0;
+    let mut divide = Vec::new();
     let mv_list = move_gen.generate_moves(board);
     let count = mv_list.get_count();
     for i in 0..count {
@@ -22,10 +25,10 @@ pub fn make_perft(depth: i32, board: &mut Board) -> PerftResult {
         let mv_result = test_perft(board, depth - 1, &move_gen);
         result += mv_result;
         board.unmake_move(mv);
-        println!("{}: {}", mv.to_str(), mv_result);
+        divide.push((*mv, mv_result));
     }
     let time = start.elapsed().as_secs_f32();
-    PerftResult { time, result }
+    PerftResult { time, result, divide }
 }
 
 fn test_perft(board: &mut Board, depth_left: i32, move_gen: &MoveGenerator) -> usize {
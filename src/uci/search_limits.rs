@@ -0,0 +1,101 @@
+use crate::moving::mv::Move;
+use crate::uci::time_manager::TimeManager;
+
+#[derive(Clone, Copy, Default)]
+pub struct Clock {
+    pub wtime: u64,
+    pub btime: u64,
+    pub winc: u64,
+    pub binc: u64,
+    pub moves_to_go: Option<u64>,
+}
+
+// everything a go command can ask for, the search ends at whichever limit is hit first.
+// with nothing set the search runs until stopped
+#[derive(Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub depth: Option<i32>,
+    pub nodes: Option<u64>,
+    // milliseconds
+    pub movetime: Option<u64>,
+    pub clock: Option<Clock>,
+    // moves, not plies
+    pub mate: Option<i32>,
+    // bestmove is held back until stop even when the search ends on its own
+    pub infinite: bool,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: i32) -> Self {
+        SearchLimits { depth: Some(depth), ..Default::default() }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        SearchLimits { nodes: Some(nodes), ..Default::default() }
+    }
+
+    // a mate in n moves needs 2n - 1 plies. mate searches run without pruning,
+    // so one that is not found within them does not exist
+    pub fn max_depth(&self) -> Option<i32> {
+        tighter(self.depth, self.mate.map(|moves| 2 * moves - 1))
+    }
+}
+
+// the smaller of two optional limits, None means unlimited
+pub fn tighter<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// the time side of the limits, the soft limit is checked between iterations
+// and the hard one by the search itself while an iteration runs
+#[derive(Clone, Copy, Default)]
+pub struct TimeLimit {
+    movetime: Option<u128>,
+    time_manager: Option<TimeManager>,
+}
+
+impl TimeLimit {
+    pub fn new(movetime: Option<u128>, time_manager: Option<TimeManager>) -> Self {
+        TimeLimit { movetime, time_manager }
+    }
+
+    pub fn soft_stop(&self, time_passed: u128) -> bool {
+        self.movetime.is_some_and(|t| time_passed >= t / 2)
+            || self.time_manager.is_some_and(|tm| tm.soft_stop(time_passed))
+    }
+
+    // the search stops itself once this many milliseconds passed
+    pub fn hard_limit(&self) -> Option<u128> {
+        tighter(self.movetime, self.time_manager.map(|tm| tm.maximum()))
+    }
+
+    pub fn update(&mut self, score: i32, best_move: Move) {
+        if let Some(tm) = self.time_manager.as_mut() {
+            tm.update(score, best_move);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SearchLimits, TimeLimit};
+
+    #[test]
+    fn should_stop_at_closest_depth_limit() {
+        let limits = SearchLimits { depth: Some(9), mate: Some(3), ..Default::default() };
+        assert_eq!(limits.max_depth(), Some(5));
+        assert_eq!(SearchLimits::depth(4).max_depth(), Some(4));
+        assert_eq!(SearchLimits::default().max_depth(), None);
+    }
+
+    #[test]
+    fn should_never_stop_without_time_limit() {
+        let time = TimeLimit::default();
+        assert!(!time.soft_stop(u128::MAX) && time.hard_limit().is_none());
+        let movetime = TimeLimit::new(Some(100), None);
+        assert!(movetime.soft_stop(50) && movetime.hard_limit() == Some(100));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::moving::mv::Move;
+
+// sudden death games are planned as if this many moves were left
+const DEFAULT_MOVES_TO_GO: u64 = 20;
+const MAX_MOVES_TO_GO: u64 = 50;
+// never plan to use more than this share of the clock on one move
+const MAX_TIME_SHARE: f64 = 0.8;
+const MAX_OPTIMUM_FACTOR: u128 = 4;
+// share of the target used while the best move stays the same, every recent change adds to it
+const STABLE_SCALE: f64 = 0.7;
+const INSTABILITY_WEIGHT: f64 = 0.6;
+// a score drop of this many centipawns doubles the time
+const SCORE_DROP_DOUBLING: i32 = 100;
+
+#[derive(Clone, Copy)]
+pub struct TimeManager {
+    optimum: u128,
+    maximum: u128,
+    best_move: Move,
+    prev_score: Option<i32>,
+    instability: f64,
+    score_scale: f64,
+}
+
+impl TimeManager {
+    pub fn new(time: u64, inc: u64, moves_to_go: Option<u64>, overhead: u64, legal_moves: usize) -> Self {
+        let available = time.saturating_sub(overhead).max(1) as u128;
+        let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, MAX_MOVES_TO_GO) as u128;
+        let maximum = (available as f64 * MAX_TIME_SHARE) as u128;
+        let optimum = (available / moves_to_go + inc as u128 * 3 / 4).min(maximum);
+        let maximum = maximum.min(optimum * MAX_OPTIMUM_FACTOR);
+        // a forced move is played without thinking
+        let optimum = if legal_moves == 1 { 0 } else { optimum };
+        TimeManager { optimum, maximum, best_move: Move::null(), prev_score: None, instability: 0.0, score_scale: 1.0 }
+    }
+
+    // called after every finished iteration, a changing best move or a falling score buys more time
+    pub fn update(&mut self, score: i32, best_move: Move) {
+        self.instability /= 2.0;
+        if !self.best_move.is_null() && best_move != self.best_move {
+            self.instability += 1.0;
+        }
+        self.best_move = best_move;
+        let drop = self.prev_score.map_or(0, |prev| (prev - score).clamp(0, SCORE_DROP_DOUBLING));
+        self.score_scale = 1.0 + drop as f64 / SCORE_DROP_DOUBLING as f64;
+        self.prev_score = Some(score);
+    }
+
+    // an iteration takes about as long as all before it, so none is started past half the target
+    pub fn soft_stop(&self, time_passed: u128) -> bool {
+        let scale = (STABLE_SCALE + self.instability * INSTABILITY_WEIGHT) * self.score_scale;
+        let target = ((self.optimum as f64 * scale) as u128).min(self.maximum);
+        time_passed >= target / 2
+    }
+
+    // the search is cut off here even in the middle of an iteration
+    pub fn maximum(&self) -> u128 {
+        self.maximum
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TimeManager;
+    use crate::moving::mv::Move;
+
+    #[test]
+    fn should_spend_more_time_with_fewer_moves_to_go() {
+        let sudden_death = TimeManager::new(60_000, 0, None, 0, 20);
+        let last_move = TimeManager::new(60_000, 0, Some(1), 0, 20);
+        assert!(last_move.optimum > sudden_death.optimum);
+        assert!(last_move.maximum < 60_000);
+    }
+
+    #[test]
+    fn should_stop_at_once_with_single_legal_move() {
+        let tm = TimeManager::new(60_000, 1_000, None, 0, 1);
+        assert!(tm.soft_stop(0));
+        assert!(tm.maximum() > 0);
+    }
+
+    #[test]
+    fn should_extend_time_when_best_move_changes() {
+        let mut stable = TimeManager::new(60_000, 0, None, 0, 20);
+        let mut unstable = stable;
+        for i in 0..6u16 {
+            stable.update(20, Move::new_quiet(12, 28));
+            unstable.update(20, Move::new_quiet(12, 20 + i));
+        }
+        let time = stable.optimum / 2;
+        assert!(stable.soft_stop(time));
+        assert!(!unstable.soft_stop(time));
+    }
+}
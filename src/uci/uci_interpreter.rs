@@ -1,364 +1,258 @@
-use regex::Regex;
-
+use super::command::{GoCommand, UciCommand, UciError, START_POS};
 use super::engine::Engine;
-use crate::{tests::{itflat::make_comp_tests, nps::make_nps, test_suites::NOLOT, transpositions::test_transpositions, wac::wac_test}, uci::perft::make_perft};
+use super::message::{write_line, Output, UciMessage};
+use super::options::OptionRegistry;
+use super::uci_observer::UciObserver;
+use crate::{tests::{itflat::make_comp_tests, nps::make_nps, test_suites::NOLOT, skill_match::skill_match, transpositions::test_transpositions, wac::wac_test}, uci::perft::make_perft};
 use std::{
-    io::{self, Write}, process::exit
+    fmt::Display, io::{self, BufRead, Write}, sync::{mpsc::{channel, Receiver}, Arc, Mutex}, thread::spawn
 };
 
-const START_POS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const ENGINE_NAME: &str = "Barbel";
+const ENGINE_AUTHOR: &str = "s3r10us3r";
 
 
 pub struct UciController {
     engine: Engine,
-    parsed_command: Option<String>
+    options: OptionRegistry,
+    output: Output,
 }
 
 impl Default for UciController {
     fn default() -> Self {
-        UciController::new()
+        UciController::new(io::stdout())
     }
 }
 
 impl UciController {
-    pub fn new() -> Self {
+    // search output goes to the same stream as the answers to commands
+    pub fn new<W: Write + Send + 'static>(output: W) -> Self {
+        let output: Output = Arc::new(Mutex::new(output));
         let mut engine = Engine::new();
+        engine.set_observer(Arc::new(UciObserver::new(output.clone())));
         let _ = engine.set_pos(START_POS);
-        UciController { engine, parsed_command: None }
+        UciController { engine, options: OptionRegistry::default(), output }
     }
 
-    pub fn run(&mut self) {
-        loop {
-            let mut input = String::new();
-            io::stdout().flush().unwrap();
-            let rl = io::stdin().read_line(&mut input);
-            match rl {
-                Err(e) => {
-                    println!("INPUT ERROR: {e:?}");
-                    return;
-                }
-                Ok(us) => {
-                    if us == 0 {
-                        return;
-                    }
-                    let normalized_command = merge_spaces(input);
-                    self.exec_command(normalized_command);
-                }
+    // commands are read on their own thread, so the search never holds up reading.
+    // returns on quit or when the input ends, a running search sends its bestmove first.
+    // input may end right after go, a search with limits then still runs to its end
+    pub fn run<R: BufRead + Send + 'static>(&mut self, input: R) {
+        for line in spawn_reader(input, self.output.clone()) {
+            if !self.exec_command(&line) {
+                self.engine.stop();
+                return;
             }
         }
+        self.engine.finish();
     }
 
-    fn exec_command(&mut self, command: String ) {
-        self.parsed_command = Some(command);
-        let token = self.pop_token();
-        if let Some(t) = token {
-            match t.as_str() {
-                "go" => self.go(),
-                "position" => self.position(),
-                "isready" => self.is_ready(),
-                "stop" => self.stop(),
-                "help" => self.help(),
-                "uci" => self.uci(),
-                "quit" => self.quit(),
-                "ucinewgame" => self.ucinewgame(),
-                "wac" => wac_test(),
-                "nps" => nps_test(),
-                "tt_test"=> self.tt_test(),
-                "iter_test" => self.iter_test(),
-                _ => self.invalid_command(&t),
-            }
+    // bad input is reported to the gui and otherwise ignored, the engine keeps its state
+    fn exec_command(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
         }
+        line.parse().and_then(|command| self.execute(command)).unwrap_or_else(|e| {
+            self.write(UciMessage::InfoString(&e.to_string()));
+            true
+        })
     }
 
-    fn tt_test(&mut self) {
-        let token = self.pop_token();
-        match token {
-            Some(t) => {
-                let num = t.parse::<i32>();
-                if let Ok(n) = num {
-                    let board = self.engine.get_board_mut();
-                    let (false_positives, false_negatives) = test_transpositions(board, n);
-                    println!("Found {false_positives} false positives and {false_negatives} false negatives at depth {n}");
-                } else {
-                    println!("Invalid argument");
-                }
+    // false once the controller should stop reading commands
+    fn execute(&mut self, command: UciCommand) -> Result<bool, UciError> {
+        match command {
+            UciCommand::Uci => self.uci(),
+            // we have no extra debug output
+            UciCommand::Debug(_) => {}
+            UciCommand::IsReady => self.is_ready(),
+            UciCommand::SetOption { name, value } => {
+                self.options.set(&mut self.engine, &name, &value).map_err(UciError::InvalidOption)?
+            }
+            UciCommand::UciNewGame => self.ucinewgame(),
+            UciCommand::Position { fen, moves } => self.engine.set_position(&fen, &moves)?,
+            UciCommand::Go(go) => self.go(go),
+            UciCommand::Stop => self.engine.stop(),
+            UciCommand::PonderHit => self.engine.ponderhit(),
+            UciCommand::Quit => return Ok(false),
+            UciCommand::Help => self.help(),
+            UciCommand::Perft(depth) => self.go_perft(depth),
+            UciCommand::Wac => wac_test(self.engine.get_extensions(), &self.output),
+            UciCommand::Nps => self.nps_test(),
+            UciCommand::TtTest(depth) => self.tt_test(depth),
+            UciCommand::IterTest => make_comp_tests(&self.output),
+            UciCommand::SkillMatch { a, b, rounds } => {
+                let (score, elo) = skill_match(a, b, rounds);
+                self.write(format!("Level {a} vs level {b}: score {score:.3}, elo difference {elo:.0}"));
             }
-            _ => println!("Please provide an argument!")
         }
+        Ok(true)
     }
 
-    fn iter_test(&mut self) {
-        make_comp_tests();
+    fn write(&self, line: impl Display) {
+        write_line(&self.output, line);
     }
 
-    fn go(&mut self) {
-        let token = self.pop_token();
-        match token {
-            Some(t) => {
-                match t.as_str() {
-                    "perft" => self.go_perft(),
-                    "depth" => self.go_depth(),
-                    "wtime" | "btime" | "winc" | "binc" => {
-                        self.push_token_to_front(t);
-                        self.go_time();
-                    }
-                    "movetime" => self.go_movetime(),
-                    _ => panic!()
-                }
-            }
-            None => {
-                self.go_infinite();
-            }
-        }
+    fn tt_test(&mut self, depth: i32) {
+        let board = self.engine.get_board_mut();
+        let (false_positives, false_negatives) = test_transpositions(board, depth);
+        self.write(format!("Found {false_positives} false positives and {false_negatives} false negatives at depth {depth}"));
     }
 
-    fn go_infinite(&mut self) {
-        self.engine.search_infinite();
+    fn nps_test(&self) {
+        let nps_result = make_nps(NOLOT);
+        let nps = nps_result.nodes as f64 / (nps_result.time as f64 / 1000.);
+        self.write(format!("\nNodes searched: {}\nTime measured: {:.2}s\nNodes per second: {:.2}", nps_result.nodes, nps_result.time as f64 / 1000., nps));
     }
 
-    fn go_perft(&mut self) {
-        let token = self.pop_token();
-        match token {
-            Some(t) => {
-                let parse_result = t.parse::<i32>();
-                if let Ok(depth) = parse_result {
-                    let board = self.engine.get_board_mut();
-                    let result = make_perft(depth, board);
-                    println!("\nNodes searched: {}, Time: {}\n\n", result.result, result.time);
-                } else {
-                    println!("Invalid argument!\n");
-                }
-            }
-            None => {
-                self.reset();
-            }
+    // a search that is still running ends before the next one is set up, so ponderhit
+    // and stop only ever reach the latest search. bad searchmoves are reported and skipped,
+    // every go ends in a bestmove
+    fn go(&mut self, go: GoCommand) {
+        self.engine.stop();
+        for e in self.engine.set_search_moves(&go.search_moves) {
+            self.write(UciMessage::InfoString(&e.to_string()));
         }
+        self.engine.set_ponder(go.ponder);
+        self.engine.search(go.limits);
     }
 
-    fn go_depth(&mut self) {
-        let token = self.pop_token();
-        match token {
-            Some(t) => {
-                let parse_result = t.parse::<i32>();
-                if let Ok(depth) = parse_result {
-                    if depth <= 0 {
-                        println!("Invalid depth {}, depth must be at least 1", { depth });
-                        self.reset();
-                    }
-                    self.engine.search_to_depth(depth);
-                } else {
-                    println!("Invalid argument!\n");
-                }
-            }
-            None => {
-                self.reset();
-            }
+    fn go_perft(&mut self, depth: i32) {
+        let board = self.engine.get_board_mut();
+        let result = make_perft(depth, board);
+        for (mv, nodes) in &result.divide {
+            self.write(format!("{}: {nodes}", mv.to_str()));
         }
+        self.write(format!("\nNodes searched: {}, Time: {}\n\n", result.result, result.time));
     }
 
-    fn go_movetime(&mut self) {
-        if let Some(num) = self.pop_token() {
-            let time_res = num.parse();
-            if let Ok(time) = time_res {
-                self.engine.search_movetime(time);
-            }
-        }
+    fn ucinewgame(&mut self) {
+        self.engine.new_game();
+        _ = self.engine.set_pos(START_POS);
     }
 
-
-    fn go_time(&mut self) {
-        let mut wtime: u64 = 0;
-        let mut btime: u64 = 0;
-        let mut winc: u64 = 0;
-        let mut binc: u64 = 0;
-
-        for _ in 0..4 {
-            if let (Some(t), Some(val)) = (self.pop_token(), self.pop_token()) {
-                let num = val.parse::<u64>().unwrap_or_default();
-                match t.as_str() {
-                    "wtime" => wtime = num,
-                    "btime" => btime = num,
-                    "winc" => winc = num,
-                    "binc" => binc = num,
-                    _ => {}
-                }
-            }
+    fn uci(&mut self) {
+        let name = format!("{ENGINE_NAME} {}", env!("CARGO_PKG_VERSION"));
+        self.write(UciMessage::Id { name: &name, author: ENGINE_AUTHOR });
+        for option in self.options.iter() {
+            self.write(UciMessage::Option(option));
         }
-        println!("{} {} {} {}", wtime, btime, winc, binc);
-        self.engine.search_with_time(wtime, btime, winc, binc);
+        self.write(UciMessage::UciOk);
     }
 
-    fn position(&mut self) {
-        let token = self.pop_token();
-        if let Some(p) = token {
-            match p.as_str() {
-                "startpos" => self.position_startpos(),
-                "fen" => self.position_fen(),
-                _ => {
-                    self.reset();
-                }
-            }
-        } else {
-            self.reset();
-        }
+    // answered right away, also while searching
+    fn is_ready(&mut self) {
+        self.write(UciMessage::ReadyOk);
     }
 
-    fn position_startpos(&mut self) {
-        let token = self.pop_token();
-        match token {
-            Some(t) => {
-                let mut moves = vec![];
-                if t.as_str() == "moves" {
-                    loop {
-                        let token = self.pop_token();
-                        if let Some(t) = token {
-                            moves.push(t);
-                        } else {
-                            break;
-                        }
-                    }
-                    self.position_with_fen(START_POS.to_owned(), moves);
-                }
-            } 
-            None => self.position_with_fen(START_POS.to_owned(), vec![]),
-        }
+    fn help(&self) {
+        self.write("BARBEL, THE BEST CHESS ENGINE");
     }
+}
 
-    fn position_fen(&mut self) {
-        let mut fen_vec = Vec::new();
-        loop {
-            let token = self.pop_token();
-            match token {
-                Some(t) => {
-                    match t.as_str() {
-                        "moves" => {
-                            let moves = self.parse_moves();
-                            self.position_with_fen(fen_vec.join(" "), moves);
-                            break;
-                        },
-                        _ => fen_vec.push(t.to_owned()),
-                    }
-                }
-                None => {
-                    self.position_with_fen(fen_vec.join(" "), vec![]);
+// forwards the input line by line until it ends
+fn spawn_reader<R: BufRead + Send + 'static>(input: R, output: Output) -> Receiver<String> {
+    let (lines, receiver) = channel();
+    spawn(move || {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    write_line(&output, UciMessage::InfoString(&format!("Input error: {e}")));
                     break;
                 }
+            };
+            // the controller is gone once run returned
+            if lines.send(line).is_err() {
+                break;
             }
         }
+    });
+    receiver
+}
 
-    }
+#[cfg(test)]
+pub(crate) mod test {
+    use std::io::{Cursor, Write};
+    use std::sync::{Arc, Mutex};
 
-    //this assumes the 'moves' string is already parsed
-    fn parse_moves(&mut self) -> Vec<String> {
-        let mut moves = vec![];
-        loop {
-            let token = self.pop_token();
-            match token {
-                Some(t) => {
-                    moves.push(t);
-                }
-                None => { break; }
-            }
-        }
-        moves
-    }
+    use super::UciController;
 
-    fn position_with_fen(&mut self, fen: String, moves: Vec<String>) {
-        let res = self.engine.set_pos(fen.as_str());
-        if let Err(e) = res {
-            println!("Invalid fen!");
-            println!("{e:?}");
-            self.reset();
-            return;
-        }
-        for move_str in moves {
-            let mv_s = move_str.as_str();
-            let err = self.engine.make_move(mv_s);
-            if let Err(e) = err {
-                panic!("Invalid move! {e:?}");
-            }
-        }
-    }
+    // an output the test can read back while the controller owns a clone
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
 
-    fn reset(&mut self) {
-        self.parsed_command = None;
+    impl SharedBuffer {
+        pub(crate) fn lines(&self) -> Vec<String> {
+            let bytes = self.0.lock().unwrap().clone();
+            String::from_utf8(bytes).unwrap().lines().map(String::from).collect()
+        }
     }
 
-    fn pop_token(&mut self) -> Option<String> {
-        let parsed_command = self.parsed_command.clone();
-        match parsed_command {
-            Some(command) => {
-                match command.split_once(' ') {
-                    Some((next, rest)) => {
-                        if next.is_empty() {
-                            self.parsed_command = None;
-                            None
-                        } else {
-                            self.parsed_command = Some(rest.to_owned());
-                            Some(next.to_owned())
-                        }
-                    }
-                    None => {
-                        if command.is_empty() { None } 
-                        else { 
-                            self.parsed_command = None;
-                            Some(command.to_owned()) 
-                        }
-                    }
-                }
-            },
-            None => None
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
         }
-    }
 
-    fn push_token_to_front(&mut self, token: String) {
-        let parsed_command = self.parsed_command.clone();
-        match parsed_command {
-            Some(s) => self.parsed_command = Some(token + " " + s.as_str()),
-            None => self.parsed_command = Some(token),
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
         }
     }
 
-    fn ucinewgame(&mut self) {
-        self.engine = Engine::new();
-        _ = self.engine.set_pos(START_POS);
+    fn session(input: &str) -> Vec<String> {
+        let output = SharedBuffer::default();
+        UciController::new(output.clone()).run(Cursor::new(input.to_owned()));
+        output.lines()
     }
 
-    fn quit(&mut self) {
-        exit(0);
+    #[test]
+    fn should_answer_handshake_and_report_bad_input() {
+        let lines = session("uci\nposition startpos moves e2e5\nisready\nquit\nisready\n");
+        assert!(lines[0].starts_with("id name Barbel"));
+        assert_eq!(&lines[lines.len() - 3..], ["uciok", "info string Illegal move: 'e2e5'", "readyok"]);
     }
 
-    fn uci(&mut self) {
-        println!("uciok");
+    #[test]
+    fn should_hold_bestmove_of_infinite_search_until_stop() {
+        let lines = session("position startpos moves e2e4\ngo infinite\nisready\nstop\nisready\n");
+        let ready: Vec<usize> = (0..lines.len()).filter(|&i| lines[i] == "readyok").collect();
+        let best: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].starts_with("bestmove ")).collect();
+        assert_eq!((ready.len(), best.len()), (2, 1));
+        assert!(ready[0] < best[0] && best[0] < ready[1]);
     }
 
-    fn is_ready(&mut self) {
-        if !self.engine.is_running() {
-            println!("readyok");
+    #[test]
+    fn should_reject_impossible_positions_and_answer_mated_ones_with_a_null_move() {
+        let lines = session("position fen 8/8/8/3kK3/8/8/8/8 w - - 0 1\nisready\n");
+        assert!(lines[0].starts_with("info string Invalid fen"));
+        for go in ["go depth 10", "go wtime 1000 btime 1000", "go movetime 500"] {
+            let input = format!("position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3\n{go}\n");
+            assert_eq!(session(&input), ["bestmove 0000"]);
         }
     }
 
-    fn stop(&mut self) {
-        self.engine.stop();
+    #[test]
+    fn should_skip_bad_search_moves_and_still_send_bestmove() {
+        let lines = session("go depth 3 searchmoves e2e5 b1c3 xyz\n");
+        assert_eq!(lines[..2], ["info string Illegal move: 'e2e5'", "info string Invalid move: 'xyz'"]);
+        assert!(lines.last().unwrap().starts_with("bestmove b1c3"));
+        let lines = session("go depth 3 searchmoves e7e5\n");
+        assert_eq!(lines[0], "info string Illegal move: 'e7e5'");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
     }
 
-    fn help(&self) {
-        println!("BARBEL, THE BEST CHESS ENGINE");
+    #[test]
+    fn should_finish_searches_with_limits_when_input_ends() {
+        let lines = session("go depth 4\n");
+        assert!(lines.iter().any(|line| line.starts_with("info depth 4 ") && !line.contains("bound ")));
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+        let lines = session("go infinite\n");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
     }
 
-    fn invalid_command(&self, command: &str) {
-        println!("Unknown command: '{command}'. Type help for more information.");
+    #[test]
+    fn should_write_perft_breakdown_to_the_output() {
+        let lines = session("go perft 2\n");
+        assert_eq!(lines.iter().filter(|line| line.ends_with(": 20")).count(), 20);
+        assert!(lines.iter().any(|line| line.starts_with("Nodes searched: 400,")));
     }
 }
-
-fn merge_spaces(s: String) -> String {
-    let s = s.trim();
-    let re = Regex::new(r"\s+").unwrap();
-    re.replace_all(s, " ").to_string()
-}
-
-fn nps_test() {
-    let nps_result = make_nps(NOLOT);
-    let nps = nps_result.nodes as f64 / (nps_result.time as f64 / 1000.);
-    println!("\nNodes searched: {}\nTime measured: {:.2}s\nNodes per second: {:.2}", nps_result.nodes, nps_result.time as f64 / 1000., nps);
-}
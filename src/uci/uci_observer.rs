@@ -0,0 +1,24 @@
+use crate::search::observer::{SearchInfo, SearchObserver};
+use crate::moving::mv::Move;
+use crate::uci::message::{write_line, Output, UciMessage};
+
+// writes search progress as uci info and bestmove lines
+pub struct UciObserver {
+    output: Output,
+}
+
+impl UciObserver {
+    pub fn new(output: Output) -> Self {
+        UciObserver { output }
+    }
+}
+
+impl SearchObserver for UciObserver {
+    fn on_info(&self, info: &SearchInfo) {
+        write_line(&self.output, UciMessage::Info(info));
+    }
+
+    fn on_best_move(&self, best_move: Move, ponder_move: Option<Move>) {
+        write_line(&self.output, UciMessage::BestMove { best_move, ponder_move });
+    }
+}
@@ -0,0 +1,4 @@
+pub mod command;
+pub mod message;
+pub mod xboard_interpreter;
+pub mod xboard_observer;
@@ -0,0 +1,138 @@
+use std::str::{FromStr, SplitWhitespace};
+
+use crate::uci::command::is_move;
+
+// a gui to engine line in the xboard protocol (cecp) after parsing
+pub enum XBoardCommand {
+    XBoard,
+    Protover(u32),
+    New,
+    Force,
+    Go,
+    UserMove(String),
+    // base time and increment in milliseconds, 0 moves per session means the whole game
+    Level { moves_per_session: u64, base: u64, increment: u64 },
+    // exact milliseconds per move
+    St(u64),
+    Sd(i32),
+    // both clocks in milliseconds, xboard sends centiseconds
+    Time(u64),
+    Otim(u64),
+    Post,
+    NoPost,
+    Undo,
+    Remove,
+    SetBoard(String),
+    Result { result: String, comment: String },
+    Ping(i64),
+    // '?', play the best move found so far
+    MoveNow,
+    Quit,
+    // accepted, rejected, random, computer, hard, easy and the like need no answer
+    Ignored,
+}
+
+// the parse errors, worded the way the protocol reports them
+pub const UNKNOWN_COMMAND: &str = "unknown command";
+pub const MISSING_ARGUMENT: &str = "missing argument";
+pub const INVALID_ARGUMENT: &str = "invalid argument";
+
+impl FromStr for XBoardCommand {
+    type Err = &'static str;
+
+    fn from_str(line: &str) -> Result<Self, &'static str> {
+        let mut tokens = line.split_whitespace();
+        let Some(command) = tokens.next() else {
+            return Err(UNKNOWN_COMMAND);
+        };
+        let parsed = match command {
+            "xboard" => XBoardCommand::XBoard,
+            "protover" => XBoardCommand::Protover(parse(tokens.next())?),
+            "new" => XBoardCommand::New,
+            "force" => XBoardCommand::Force,
+            "go" => XBoardCommand::Go,
+            "usermove" => XBoardCommand::UserMove(parse_move(tokens.next())?),
+            "level" => XBoardCommand::Level {
+                moves_per_session: parse(tokens.next())?,
+                base: parse_minutes(tokens.next())?,
+                increment: parse_seconds(tokens.next())?,
+            },
+            "st" => XBoardCommand::St(parse_seconds(tokens.next())?),
+            "sd" => XBoardCommand::Sd(parse(tokens.next())?),
+            "time" => XBoardCommand::Time(parse_centiseconds(tokens.next())?),
+            "otim" => XBoardCommand::Otim(parse_centiseconds(tokens.next())?),
+            "post" => XBoardCommand::Post,
+            "nopost" => XBoardCommand::NoPost,
+            "undo" => XBoardCommand::Undo,
+            "remove" => XBoardCommand::Remove,
+            "setboard" => XBoardCommand::SetBoard(rest(tokens)),
+            "result" => XBoardCommand::Result { result: tokens.next().ok_or(MISSING_ARGUMENT)?.to_owned(), comment: rest(tokens) },
+            "ping" => XBoardCommand::Ping(parse(tokens.next())?),
+            "?" => XBoardCommand::MoveNow,
+            "quit" => XBoardCommand::Quit,
+            "accepted" | "rejected" | "random" | "computer" | "hard" | "easy" | "name" | "rating" | "ics" | "draw" => XBoardCommand::Ignored,
+            // protocol version 1 guis send moves without usermove in front
+            mv if is_move(mv) => XBoardCommand::UserMove(mv.to_owned()),
+            _ => return Err(UNKNOWN_COMMAND),
+        };
+        Ok(parsed)
+    }
+}
+
+fn rest(tokens: SplitWhitespace) -> String {
+    tokens.collect::<Vec<_>>().join(" ")
+}
+
+fn parse<T: FromStr>(token: Option<&str>) -> Result<T, &'static str> {
+    token.ok_or(MISSING_ARGUMENT)?.parse().map_err(|_| INVALID_ARGUMENT)
+}
+
+fn parse_move(token: Option<&str>) -> Result<String, &'static str> {
+    let mv = token.ok_or(MISSING_ARGUMENT)?;
+    if is_move(mv) { Ok(mv.to_owned()) } else { Err(INVALID_ARGUMENT) }
+}
+
+// seconds may have a fraction, e.g. an increment of 0.5
+fn parse_seconds(token: Option<&str>) -> Result<u64, &'static str> {
+    let seconds: f64 = parse(token)?;
+    if seconds < 0. { Err(INVALID_ARGUMENT) } else { Ok((seconds * 1000.) as u64) }
+}
+
+// minutes or minutes:seconds, as in 'level 40 5:30 0'
+fn parse_minutes(token: Option<&str>) -> Result<u64, &'static str> {
+    let token = token.ok_or(MISSING_ARGUMENT)?;
+    let (minutes, seconds) = token.split_once(':').unwrap_or((token, "0"));
+    let minutes: u64 = minutes.parse().map_err(|_| INVALID_ARGUMENT)?;
+    let seconds: u64 = seconds.parse().map_err(|_| INVALID_ARGUMENT)?;
+    Ok((minutes * 60 + seconds) * 1000)
+}
+
+// clocks can run negative, that counts as no time left
+fn parse_centiseconds(token: Option<&str>) -> Result<u64, &'static str> {
+    let centiseconds: i64 = parse(token)?;
+    Ok(centiseconds.max(0) as u64 * 10)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{XBoardCommand, INVALID_ARGUMENT, UNKNOWN_COMMAND};
+
+    #[test]
+    fn should_convert_time_controls_to_milliseconds() {
+        let Ok(XBoardCommand::Level { moves_per_session, base, increment }) = "level 40 5:30 0.5".parse() else {
+            panic!("expected level");
+        };
+        assert_eq!((moves_per_session, base, increment), (40, 330_000, 500));
+        assert!(matches!("time -30".parse(), Ok(XBoardCommand::Time(0))));
+        assert!(matches!("otim 1234".parse(), Ok(XBoardCommand::Otim(12_340))));
+        assert!(matches!("st 2".parse(), Ok(XBoardCommand::St(2000))));
+    }
+
+    #[test]
+    fn should_parse_moves_with_and_without_usermove() {
+        assert!(matches!("usermove e7e8q".parse(), Ok(XBoardCommand::UserMove(mv)) if mv == "e7e8q"));
+        assert!(matches!("e2e4".parse(), Ok(XBoardCommand::UserMove(mv)) if mv == "e2e4"));
+        assert!(matches!("usermove Nf3".parse::<XBoardCommand>(), Err(INVALID_ARGUMENT)));
+        assert!(matches!("fly".parse::<XBoardCommand>(), Err(UNKNOWN_COMMAND)));
+    }
+}
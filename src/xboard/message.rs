@@ -0,0 +1,64 @@
+use std::fmt;
+
+use crate::moving::mv::Move;
+use crate::search::observer::{Score, SearchInfo};
+
+// xboard reads mates as scores past 100000
+const MATE_SCORE: i32 = 100_000;
+
+// an engine to gui message in the xboard protocol, displaying it gives the line to send
+pub enum XBoardMessage<'a> {
+    // the answer to protover, done=1 tells the gui it can go on
+    Features { name: &'a str },
+    Pong(i64),
+    Move(Move),
+    // post output: depth, score, time in centiseconds, nodes and the pv
+    Thinking(&'a SearchInfo),
+    IllegalMove(&'a str),
+    Error { reason: &'a str, command: &'a str },
+    TellUserError(&'a str),
+    Result { result: &'a str, comment: &'a str },
+}
+
+impl fmt::Display for XBoardMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XBoardMessage::Features { name } => write!(
+                f,
+                "feature myname=\"{name}\" ping=1 setboard=1 usermove=1 time=1 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 variants=\"normal\" done=1"
+            ),
+            XBoardMessage::Pong(n) => write!(f, "pong {n}"),
+            XBoardMessage::Move(mv) => write!(f, "move {}", mv.to_str()),
+            XBoardMessage::Thinking(info) => {
+                let score = match info.score {
+                    Score::Cp(cp) => cp,
+                    Score::Mate(moves) if moves > 0 => MATE_SCORE + moves,
+                    Score::Mate(moves) => -MATE_SCORE + moves,
+                };
+                let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_str()).collect();
+                write!(f, "{} {} {} {} {}", info.depth, score, info.time / 10, info.nodes, pv.join(" "))
+            }
+            XBoardMessage::IllegalMove(mv) => write!(f, "Illegal move: {mv}"),
+            XBoardMessage::Error { reason, command } => write!(f, "Error ({reason}): {command}"),
+            XBoardMessage::TellUserError(text) => write!(f, "tellusererror {text}"),
+            XBoardMessage::Result { result, comment } => write!(f, "{result} {{{comment}}}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::XBoardMessage;
+    use crate::moving::mv::Move;
+    use crate::search::observer::{Bound, Score, SearchInfo};
+
+    #[test]
+    fn should_post_thinking_in_centiseconds_with_mate_scores() {
+        let info = SearchInfo {
+            depth: 7, seldepth: 9, multipv: 1, score: Score::Mate(-3), bound: Bound::Exact,
+            nodes: 5000, nps: 0, time: 1234, hashfull: 0, pv: vec![Move::new_quiet(12, 28)],
+        };
+        assert_eq!(XBoardMessage::Thinking(&info).to_string(), "7 -100003 123 5000 e2e4");
+        assert_eq!(XBoardMessage::Result { result: "1-0", comment: "White mates" }.to_string(), "1-0 {White mates}");
+    }
+}
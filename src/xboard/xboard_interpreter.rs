@@ -0,0 +1,409 @@
+use super::command::XBoardCommand;
+use super::message::XBoardMessage;
+use super::xboard_observer::XBoardObserver;
+use crate::constants::{BLACK, WHITE};
+use crate::moving::move_generation::generate_moves;
+use crate::moving::mv::Move;
+use crate::position::board::Board;
+use crate::uci::command::{UciError, START_POS};
+use crate::uci::engine::Engine;
+use crate::uci::message::{write_line, Output};
+use crate::uci::search_limits::{Clock, SearchLimits};
+use std::{
+    io::{BufRead, Write}, sync::{atomic::{AtomicBool, Ordering}, mpsc::{channel, Sender}, Arc, Mutex}, thread::spawn
+};
+
+const ENGINE_NAME: &str = "Barbel";
+
+// what xboard uses until it sends a level command, 40 moves in 5 minutes
+const DEFAULT_MOVES_PER_SESSION: u64 = 40;
+const DEFAULT_BASE_TIME: u64 = 300_000;
+
+// the controller handles lines from the gui and moves from the search in one place
+pub enum Event {
+    Line(String),
+    EngineMove(Move),
+    InputClosed,
+}
+
+pub struct XBoardController {
+    engine: Engine,
+    output: Output,
+    post: Arc<AtomicBool>,
+    // set while a search is stopped whose move must not be played
+    abandoned: Arc<AtomicBool>,
+    // the side the engine plays, None in force mode
+    engine_side: Option<usize>,
+    thinking: bool,
+    // the game so far, undo replays it without the last moves
+    start_fen: String,
+    moves: Vec<String>,
+    // milliseconds
+    moves_per_session: u64,
+    base_time: u64,
+    increment: u64,
+    time_left: u64,
+    opponent_time: u64,
+    move_time: Option<u64>,
+    depth: Option<i32>,
+}
+
+impl Default for XBoardController {
+    fn default() -> Self {
+        XBoardController::new(std::io::stdout())
+    }
+}
+
+impl XBoardController {
+    pub fn new<W: Write + Send + 'static>(output: W) -> Self {
+        let mut engine = Engine::new();
+        let _ = engine.set_pos(START_POS);
+        XBoardController {
+            engine,
+            output: Arc::new(Mutex::new(output)),
+            post: Arc::new(AtomicBool::new(false)),
+            abandoned: Arc::new(AtomicBool::new(false)),
+            engine_side: Some(BLACK),
+            thinking: false,
+            start_fen: START_POS.to_owned(),
+            moves: Vec::new(),
+            moves_per_session: DEFAULT_MOVES_PER_SESSION,
+            base_time: DEFAULT_BASE_TIME,
+            increment: 0,
+            time_left: DEFAULT_BASE_TIME,
+            opponent_time: DEFAULT_BASE_TIME,
+            move_time: None,
+            depth: None,
+        }
+    }
+
+    // returns on quit or when the input ends
+    pub fn run<R: BufRead + Send + 'static>(&mut self, input: R) {
+        let (events, receiver) = channel();
+        let observer = XBoardObserver::new(self.output.clone(), self.post.clone(), self.abandoned.clone(), events.clone());
+        self.engine.set_observer(Arc::new(observer));
+        spawn_reader(input, events);
+        for event in receiver {
+            match event {
+                Event::Line(line) => {
+                    if !self.exec_command(&line) {
+                        break;
+                    }
+                }
+                Event::EngineMove(mv) => self.engine_moved(mv),
+                Event::InputClosed => break,
+            }
+        }
+        self.abandon_search();
+    }
+
+    // bad input is reported to the gui and otherwise ignored
+    fn exec_command(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+        match line.parse() {
+            Ok(command) => self.execute(command),
+            Err(reason) => {
+                self.write(XBoardMessage::Error { reason, command: line.trim() });
+                true
+            }
+        }
+    }
+
+    // false once the controller should stop reading commands
+    fn execute(&mut self, command: XBoardCommand) -> bool {
+        match command {
+            XBoardCommand::XBoard | XBoardCommand::Ignored => {}
+            XBoardCommand::Protover(_) => {
+                let name = format!("{ENGINE_NAME} {}", env!("CARGO_PKG_VERSION"));
+                self.write(XBoardMessage::Features { name: &name });
+            }
+            XBoardCommand::New => self.new_game(),
+            XBoardCommand::Force => {
+                self.abandon_search();
+                self.engine_side = None;
+            }
+            XBoardCommand::Go => {
+                self.abandon_search();
+                self.engine_side = Some(self.engine.get_board().us);
+                self.think();
+            }
+            XBoardCommand::UserMove(mv) => self.user_move(&mv),
+            XBoardCommand::Level { moves_per_session, base, increment } => {
+                self.moves_per_session = moves_per_session;
+                self.base_time = base;
+                self.increment = increment;
+                self.time_left = base;
+                self.opponent_time = base;
+                self.move_time = None;
+            }
+            XBoardCommand::St(move_time) => self.move_time = Some(move_time),
+            XBoardCommand::Sd(depth) => self.depth = (depth > 0).then_some(depth),
+            XBoardCommand::Time(time) => self.time_left = time,
+            XBoardCommand::Otim(time) => self.opponent_time = time,
+            XBoardCommand::Post => self.post.store(true, Ordering::Relaxed),
+            XBoardCommand::NoPost => self.post.store(false, Ordering::Relaxed),
+            XBoardCommand::Undo => self.take_back(1, "undo"),
+            XBoardCommand::Remove => self.take_back(2, "remove"),
+            XBoardCommand::SetBoard(fen) => self.set_board(&fen),
+            // the game is over, we wait for new
+            XBoardCommand::Result { .. } => {
+                self.abandon_search();
+                self.engine_side = None;
+            }
+            XBoardCommand::Ping(n) => self.write(XBoardMessage::Pong(n)),
+            XBoardCommand::MoveNow => self.engine.stop(),
+            XBoardCommand::Quit => return false,
+        }
+        true
+    }
+
+    fn write(&self, message: XBoardMessage) {
+        write_line(&self.output, message);
+    }
+
+    // the engine takes black, sd is forgotten but the time control stays
+    fn new_game(&mut self) {
+        self.abandon_search();
+        self.engine.new_game();
+        self.start_fen = START_POS.to_owned();
+        self.moves.clear();
+        _ = self.engine.set_position(START_POS, &[]);
+        self.engine_side = Some(BLACK);
+        self.depth = None;
+        self.time_left = self.base_time;
+        self.opponent_time = self.base_time;
+    }
+
+    fn user_move(&mut self, mv: &str) {
+        self.abandon_search();
+        if self.play(mv).is_err() {
+            self.write(XBoardMessage::IllegalMove(mv));
+            return;
+        }
+        if self.engine_side == Some(self.engine.get_board().us) {
+            self.think();
+        } else {
+            self.claim_result();
+        }
+    }
+
+    // moves of a search that was abandoned after it finished are dropped here, the gui only
+    // hears of moves that are played on our board too
+    fn engine_moved(&mut self, mv: Move) {
+        if !self.thinking {
+            return;
+        }
+        self.thinking = false;
+        if self.play(&mv.to_str()).is_ok() {
+            self.write(XBoardMessage::Move(mv));
+            self.claim_result();
+        }
+    }
+
+    // a game that is already over is claimed instead
+    fn think(&mut self) {
+        if self.claim_result() {
+            return;
+        }
+        let limits = SearchLimits {
+            depth: self.depth,
+            movetime: self.move_time,
+            clock: self.move_time.is_none().then(|| self.clock()),
+            ..Default::default()
+        };
+        self.thinking = true;
+        self.engine.search(limits);
+    }
+
+    // with a session length the clock is reset every moves_per_session moves
+    fn clock(&self) -> Clock {
+        let board = self.engine.get_board();
+        let (wtime, btime) = if board.us == WHITE {
+            (self.time_left, self.opponent_time)
+        } else {
+            (self.opponent_time, self.time_left)
+        };
+        let moves_played = board.get_state().get_move_clock().saturating_sub(1) as u64;
+        let moves_to_go = (self.moves_per_session > 0)
+            .then(|| self.moves_per_session - moves_played % self.moves_per_session);
+        Clock { wtime, btime, winc: self.increment, binc: self.increment, moves_to_go }
+    }
+
+    fn abandon_search(&mut self) {
+        self.abandoned.store(true, Ordering::Relaxed);
+        self.engine.stop();
+        self.abandoned.store(false, Ordering::Relaxed);
+        self.thinking = false;
+    }
+
+    // a move is played by replaying the game with it, an illegal one leaves the game as it was
+    fn play(&mut self, mv: &str) -> Result<(), UciError> {
+        self.moves.push(mv.to_owned());
+        let result = self.engine.set_position(&self.start_fen, &self.moves);
+        if result.is_err() {
+            self.moves.pop();
+        }
+        result
+    }
+
+    fn take_back(&mut self, plies: usize, command: &str) {
+        self.abandon_search();
+        if self.moves.len() < plies {
+            self.write(XBoardMessage::Error { reason: "no move to take back", command });
+            return;
+        }
+        self.moves.truncate(self.moves.len() - plies);
+        _ = self.engine.set_position(&self.start_fen, &self.moves);
+    }
+
+    fn set_board(&mut self, fen: &str) {
+        self.abandon_search();
+        match self.engine.set_position(fen, &[]) {
+            Ok(()) => {
+                self.start_fen = fen.to_owned();
+                self.moves.clear();
+            }
+            Err(_) => self.write(XBoardMessage::TellUserError("Illegal position")),
+        }
+    }
+
+    fn claim_result(&self) -> bool {
+        match game_result(self.engine.get_board()) {
+            Some((result, comment)) => {
+                self.write(XBoardMessage::Result { result, comment });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// the engine claims the end of the game itself, xboard answers with a result command
+fn game_result(board: &Board) -> Option<(&'static str, &'static str)> {
+    if generate_moves(board).get_count() == 0 {
+        return Some(match (board.is_check(), board.us == WHITE) {
+            (true, true) => ("0-1", "Black mates"),
+            (true, false) => ("1-0", "White mates"),
+            (false, _) => ("1/2-1/2", "Stalemate"),
+        });
+    }
+    if board.is_fifty_move_draw() {
+        Some(("1/2-1/2", "Fifty move rule"))
+    } else if board.is_insufficient_material() {
+        Some(("1/2-1/2", "Insufficient material"))
+    } else if board.is_repetition(0) {
+        Some(("1/2-1/2", "Draw by repetition"))
+    } else {
+        None
+    }
+}
+
+// forwards the input line by line and tells the controller when it ends
+fn spawn_reader<R: BufRead + Send + 'static>(input: R, events: Sender<Event>) {
+    spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if events.send(Event::Line(line)).is_err() {
+                return;
+            }
+        }
+        _ = events.send(Event::InputClosed);
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{pipe, BufReader, PipeWriter, Write};
+    use std::thread::{sleep, spawn, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    use super::XBoardController;
+    use crate::moving::move_generation::generate_moves;
+    use crate::run_with;
+    use crate::uci::uci_interpreter::test::SharedBuffer;
+
+    fn start() -> (PipeWriter, SharedBuffer, JoinHandle<()>) {
+        let (reader, writer) = pipe().unwrap();
+        let output = SharedBuffer::default();
+        let session = {
+            let output = output.clone();
+            spawn(move || run_with(BufReader::new(reader), output))
+        };
+        (writer, output, session)
+    }
+
+    fn wait_for(output: &SharedBuffer, prefix: &str) -> String {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(20) {
+            if let Some(line) = output.lines().into_iter().find(|line| line.starts_with(prefix)) {
+                return line;
+            }
+            sleep(Duration::from_millis(10));
+        }
+        panic!("no line starting with '{prefix}'");
+    }
+
+    #[test]
+    fn should_play_a_game_and_take_moves_back() {
+        let (mut input, output, session) = start();
+        input.write_all(b"xboard\nprotover 2\nnew\nsd 3\npost\nusermove e2e4\n").unwrap();
+        wait_for(&output, "move ");
+        input.write_all(b"force\nremove\nusermove e7e5\nfly\nping 7\nquit\n").unwrap();
+        session.join().unwrap();
+        let lines = output.lines();
+        assert!(lines[0].starts_with("feature myname=\"Barbel") && lines[0].ends_with("done=1"));
+        assert!(lines.iter().any(|line| line.starts_with("3 ")));
+        assert_eq!(&lines[lines.len() - 3..], ["Illegal move: e7e5", "Error (unknown command): fly", "pong 7"]);
+    }
+
+    #[test]
+    fn should_claim_mate_after_its_own_move() {
+        let (mut input, output, session) = start();
+        input.write_all(b"xboard\nsetboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\nsd 3\ngo\n").unwrap();
+        assert_eq!(wait_for(&output, "1-0"), "1-0 {White mates}");
+        drop(input);
+        session.join().unwrap();
+        assert_eq!(output.lines(), ["move a1a8", "1-0 {White mates}"]);
+    }
+
+    #[test]
+    fn should_write_only_the_moves_it_plays_on_its_board() {
+        let output = SharedBuffer::default();
+        let mut controller = XBoardController::new(output.clone());
+        controller.exec_command("force");
+        controller.exec_command("usermove e2e4");
+        let reply = *generate_moves(controller.engine.get_board()).iter().find(|mv| mv.to_str() == "e7e5").unwrap();
+        // a move sent by a search that force stopped after it finished
+        controller.engine_moved(reply);
+        assert!(output.lines().is_empty() && controller.moves == ["e2e4"]);
+        controller.thinking = true;
+        controller.engine_moved(reply);
+        assert_eq!(output.lines(), ["move e7e5"]);
+        assert_eq!(controller.moves, ["e2e4", "e7e5"]);
+    }
+
+    #[test]
+    fn should_count_moves_to_go_across_sessions() {
+        let mut controller = XBoardController::new(SharedBuffer::default());
+        for line in ["force", "level 40 5 0", "time 12000", "otim 9000"] {
+            assert!(controller.exec_command(line));
+        }
+        let clock = controller.clock();
+        assert_eq!((clock.wtime, clock.btime, clock.winc, clock.moves_to_go), (120_000, 90_000, 0, Some(40)));
+        // the last move of the first session, then the first of the second
+        controller.exec_command("setboard rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 40");
+        controller.exec_command("usermove e2e4");
+        let clock = controller.clock();
+        assert_eq!((clock.wtime, clock.btime, clock.moves_to_go), (90_000, 120_000, Some(1)));
+        controller.exec_command("usermove e7e5");
+        assert_eq!(controller.clock().moves_to_go, Some(40));
+        controller.exec_command("setboard rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 95");
+        assert_eq!(controller.clock().moves_to_go, Some(26));
+        // level resets both clocks, without a session length the increment carries the game
+        controller.exec_command("level 0 2 12");
+        let clock = controller.clock();
+        assert_eq!((clock.wtime, clock.btime, clock.binc, clock.moves_to_go), (120_000, 120_000, 12_000, None));
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use crate::moving::mv::Move;
+use crate::search::observer::{Bound, SearchInfo, SearchObserver};
+use crate::uci::message::{write_line, Output};
+use crate::xboard::message::XBoardMessage;
+use crate::xboard::xboard_interpreter::Event;
+
+// writes thinking output while post is on and hands the move the search ends with to the
+// controller, unless the controller abandoned the search
+pub struct XBoardObserver {
+    output: Output,
+    post: Arc<AtomicBool>,
+    abandoned: Arc<AtomicBool>,
+    moves: Sender<Event>,
+}
+
+impl XBoardObserver {
+    pub fn new(output: Output, post: Arc<AtomicBool>, abandoned: Arc<AtomicBool>, moves: Sender<Event>) -> Self {
+        XBoardObserver { output, post, abandoned, moves }
+    }
+}
+
+impl SearchObserver for XBoardObserver {
+    // xboard has no bounds, only finished lines of the best move are posted
+    fn on_info(&self, info: &SearchInfo) {
+        if self.post.load(Ordering::Relaxed) && info.bound == Bound::Exact && info.multipv == 1 {
+            write_line(&self.output, XBoardMessage::Thinking(info));
+        }
+    }
+
+    // the controller writes the move and plays it on its board, so both stay in step
+    fn on_best_move(&self, best_move: Move, _ponder_move: Option<Move>) {
+        if self.abandoned.load(Ordering::Relaxed) || best_move.is_null() {
+            return;
+        }
+        _ = self.moves.send(Event::EngineMove(best_move));
+    }
+}